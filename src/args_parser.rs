@@ -1,12 +1,23 @@
-use clap::{CommandFactory, Parser, Subcommand};
+use clap::{Args, CommandFactory, Parser, Subcommand};
 use clap_complete::{generate, Shell};
 use config::{Config, File};
 use home::home_dir;
+use serde::Deserialize;
 use std::{
+    collections::BTreeMap,
     io,
     path::{Path, PathBuf},
+    process::exit,
 };
 
+use crate::completion;
+use crate::generation::file_system::RootedFileSystem;
+use crate::generation::gen_context::{AppTarget, CtxCreationError};
+use crate::generation::graph::GraphFormat;
+use crate::generation::navigation::NavMapFormat;
+use crate::output::{Output, OutputFormat};
+use crate::wizard::NewKind;
+
 #[derive(Parser, Clone)]
 #[command(name = "Feature Generator")]
 #[command(author = "Aradi Patrik <aradipatrik@gmail.com>")]
@@ -25,6 +36,19 @@ pub struct Cli {
     #[arg(short, long)]
     pub app_name: Option<String>,
 
+    /// Print a JSON report instead of text, see the README for its schema
+    #[arg(long, value_enum, global = true, default_value = "text")]
+    pub output: OutputFormat,
+
+    /// Run as if started in DIR. The project root is the outermost directory containing a
+    /// `settings.gradle(.kts)`, looking up from DIR or from the current directory
+    #[arg(short = 'C', long, global = true, value_name = "DIR", value_parser = existing_dir)]
+    pub project_dir: Option<PathBuf>,
+
+    /// Application modules declared in the config files
+    #[arg(skip)]
+    pub app_targets: Vec<AppTarget>,
+
     #[command(subcommand)]
     pub command: Command,
 }
@@ -36,6 +60,19 @@ pub enum Command {
         /// The name of the new feature
         #[arg()]
         feature: String,
+
+        /// Application to register the feature into, as declared in the config, or `all`.
+        /// Can be repeated, the `app` module is used when missing
+        #[arg(long = "app", value_name = "NAME")]
+        apps: Vec<String>,
+
+        /// Existing library the feature depends on and gets provided by. Can be repeated
+        #[arg(long = "uses-lib", value_name = "LIB")]
+        uses_libs: Vec<String>,
+
+        /// Existing feature whose API the feature depends on, to navigate into it. Can be repeated
+        #[arg(long = "navigates-to", value_name = "FEATURE")]
+        navigates_to: Vec<String>,
     },
     /// Generates new sub-feature for a feature module
     GenSubfeat {
@@ -51,6 +88,67 @@ pub enum Command {
     GenLib {
         #[arg()]
         lib: String,
+
+        /// Application to register the library into, as declared in the config, or `all`.
+        /// Can be repeated, the `app` module is used when missing
+        #[arg(long = "app", value_name = "NAME")]
+        apps: Vec<String>,
+    },
+    /// Asks for what to generate, picking from the existing features, then previews the files
+    /// before generating them. Without a terminal every answer has to be given as an argument
+    New {
+        /// What to generate
+        #[arg(short, long, value_enum)]
+        kind: Option<NewKind>,
+
+        /// The name of the new feature, subfeature or library
+        #[arg()]
+        name: Option<String>,
+
+        /// The existing feature the new subfeature is added to
+        #[arg(short, long)]
+        feature: Option<String>,
+
+        /// Generate without asking for confirmation
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        yes: bool,
+    },
+    /// Makes an existing feature depend on a library or on the API of another feature
+    AddDep {
+        /// The feature to add the dependency to
+        #[arg(short, long)]
+        feature: String,
+
+        #[command(flatten)]
+        dependency: DependencyArgs,
+    },
+    /// Removes a dependency added by `add-dep`
+    RemoveDep {
+        /// The feature to remove the dependency from
+        #[arg(short, long)]
+        feature: String,
+
+        #[command(flatten)]
+        dependency: DependencyArgs,
+    },
+    /// Prints the dependencies between feature and library modules, flagging cycles and
+    /// dependencies on impl modules
+    Graph {
+        #[arg(short, long, value_enum, default_value = "dot")]
+        format: GraphFormat,
+    },
+    /// Prints the routes, inputs and start subfeatures of every feature, flagging duplicate routes
+    NavMap {
+        #[arg(short, long, value_enum, default_value = "markdown")]
+        format: NavMapFormat,
+    },
+    /// Lists the features with their subfeatures, and the libraries
+    List,
+    /// Answers JSON-RPC requests from IDE plugins, see the README for the methods
+    Serve {
+        /// Read requests from stdin and write responses to stdout, one per line
+        #[arg(long, action = clap::ArgAction::SetTrue, required = true)]
+        stdio: bool,
     },
     /// Adds local or global configuration
     Config {
@@ -66,10 +164,114 @@ pub enum Command {
         #[arg(short, long)]
         app_name: Option<String>,
     },
+    /// Prints a completion script, which suggests existing features and libraries too for bash,
+    /// zsh and fish
     GenerateCompletion {
         #[arg(short, long, value_enum)]
         shell: Shell,
     },
+    /// Prints the completion candidates for the last of the given words, used by the completion
+    /// scripts
+    #[command(name = "__complete", hide = true)]
+    Complete {
+        #[arg(last = true)]
+        words: Vec<String>,
+    },
+    /// Reverts the most recent generation. Created files are only deleted if they are unchanged
+    /// since, and the lines inserted into existing files are removed
+    Undo,
+    /// Re-renders previously generated files whose templates changed since, merging the
+    /// changes into local edits
+    Upgrade {
+        /// Only report what would be upgraded, without touching any file
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        dry_run: bool,
+    },
+    /// Manages the templates used for generation
+    Templates {
+        #[command(subcommand)]
+        command: TemplatesCommand,
+    },
+}
+
+impl Cli {
+    /// Directory the paths of the project are relative to. `parse_args` sets `project_dir` to the
+    /// project root, without it the current directory is used.
+    pub fn project_root(&self) -> &Path {
+        self.project_dir.as_deref().unwrap_or(Path::new(""))
+    }
+
+    /// The project, read and written relative to `project_root`
+    pub fn file_system(&self) -> RootedFileSystem {
+        RootedFileSystem::new(self.project_root())
+    }
+}
+
+impl Command {
+    /// The name the command is invoked with
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::GenFeat { .. } => "gen-feat",
+            Command::GenSubfeat { .. } => "gen-subfeat",
+            Command::GenLib { .. } => "gen-lib",
+            Command::New { .. } => "new",
+            Command::AddDep { .. } => "add-dep",
+            Command::RemoveDep { .. } => "remove-dep",
+            Command::Graph { .. } => "graph",
+            Command::NavMap { .. } => "nav-map",
+            Command::List => "list",
+            Command::Serve { .. } => "serve",
+            Command::Config { .. } => "config",
+            Command::GenerateCompletion { .. } => "generate-completion",
+            Command::Complete { .. } => "__complete",
+            Command::Undo => "undo",
+            Command::Upgrade { .. } => "upgrade",
+            Command::Templates { command } => match command {
+                TemplatesCommand::Check => "templates check",
+                TemplatesCommand::Eject { .. } => "templates eject",
+                TemplatesCommand::Diff { .. } => "templates diff",
+            },
+        }
+    }
+}
+
+#[derive(Args, Clone)]
+#[group(required = true, multiple = false)]
+pub struct DependencyArgs {
+    /// Existing library, its provider is added to the feature root too
+    #[arg(long, value_name = "LIB")]
+    pub lib: Option<String>,
+
+    /// Existing feature whose API is depended on
+    #[arg(long, value_name = "FEATURE")]
+    pub feature_api: Option<String>,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum TemplatesCommand {
+    /// Renders every built-in and user template against a sample context, and reports
+    /// undefined variables, unknown helpers and syntax errors
+    Check,
+    /// Copies built-in templates into `.feature_generator/templates`, so they can be customized
+    Eject {
+        /// The template to eject, e.g. `impl/ImplBuild` or `partials/file_header`
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        name: Option<String>,
+
+        /// Eject every built-in template and partial
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        all: bool,
+
+        /// Overwrite templates which were already ejected
+        #[arg(short, long, action = clap::ArgAction::SetTrue)]
+        force: bool,
+    },
+    /// Shows how the project's and the global templates differ from the built-in ones
+    Diff {
+        /// Only show the difference for this template
+        #[arg()]
+        name: Option<String>,
+    },
 }
 
 pub fn parse_args() -> Option<Cli> {
@@ -79,13 +281,21 @@ pub fn parse_args() -> Option<Cli> {
         debug: _,
         base_package: _,
         app_name: _,
+        output: _,
+        project_dir: _,
+        app_targets: _,
     } = args
     {
-        let mut cmd = Cli::command();
-        let name = cmd.get_name().to_string();
-        generate(shell, &mut cmd, name, &mut io::stdout());
+        if let Some(script) = completion::script(shell) {
+            print!("{}", script);
+        } else {
+            let mut cmd = Cli::command();
+            let name = cmd.get_name().to_string();
+            generate(shell, &mut cmd, name, &mut io::stdout());
+        }
         return None;
     }
+    args.project_dir = project_root(args.project_dir.as_deref());
     let config = Config::builder()
         .add_source(
             File::from(get_global_config_path())
@@ -93,7 +303,7 @@ pub fn parse_args() -> Option<Cli> {
                 .required(false),
         )
         .add_source(
-            File::with_name(".feature_generator_config.toml")
+            File::from(args.project_root().join(get_local_config_path()))
                 .format(config::FileFormat::Toml)
                 .required(false),
         )
@@ -105,10 +315,46 @@ pub fn parse_args() -> Option<Cli> {
         args.app_name = args.app_name.or(app_name.ok());
 
         args.base_package = args.base_package.or(base_package.ok());
+
+        if let Ok(apps) = conf.get::<BTreeMap<String, AppTargetConfig>>("apps") {
+            args.app_targets = apps
+                .into_iter()
+                .map(|(name, app)| AppTarget {
+                    name,
+                    module: app.module,
+                    application: app.application,
+                })
+                .collect();
+        }
     }
 
-    let is_config = matches!(&args.command, Command::Config { .. });
-    if !is_config {
+    let needs_app = !matches!(
+        &args.command,
+        Command::Config { .. }
+            | Command::Templates { .. }
+            | Command::Upgrade { .. }
+            | Command::Undo
+            | Command::Graph { .. }
+            | Command::NavMap { .. }
+            | Command::Complete { .. }
+            | Command::List
+            | Command::Serve { .. }
+    );
+    if needs_app && args.output == OutputFormat::Json {
+        let missing = if args.base_package.is_none() {
+            Some(CtxCreationError::BasePackageNameMissing)
+        } else if args.app_name.is_none() {
+            Some(CtxCreationError::AppNameMissing)
+        } else {
+            None
+        };
+        if let Some(err) = missing {
+            let mut output = Output::new(args.output, args.command.name());
+            output.error(err.code(), err);
+            exit(output.finish(1));
+        }
+    }
+    if needs_app {
         args.base_package.as_ref().expect(
             "\
     No base-package found either in arguments or config files. Please consider adding base-package \
@@ -125,6 +371,42 @@ pub fn parse_args() -> Option<Cli> {
     Some(args)
 }
 
+/// An `[apps.<name>]` table of the config
+#[derive(Deserialize)]
+struct AppTargetConfig {
+    module: PathBuf,
+    application: Option<PathBuf>,
+}
+
+/// The outermost directory among `dir` and its ancestors containing a `settings.gradle(.kts)`, so
+/// `buildSrc` and included builds resolve to the build containing them
+pub fn find_project_root(dir: &Path) -> Option<PathBuf> {
+    let dir = dir.canonicalize().ok()?;
+    dir.ancestors()
+        .filter(|ancestor| {
+            ancestor.join("settings.gradle.kts").is_file()
+                || ancestor.join("settings.gradle").is_file()
+        })
+        .last()
+        .map(Path::to_path_buf)
+}
+
+/// The project root, looked up from `project_dir` or from the current directory. Outside of a
+/// project, `project_dir` is used as it is, or `None` keeps the current directory.
+fn project_root(project_dir: Option<&Path>) -> Option<PathBuf> {
+    let start = project_dir.unwrap_or(Path::new("."));
+    find_project_root(start).or_else(|| project_dir.map(Path::to_path_buf))
+}
+
+fn existing_dir(dir: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(dir);
+    if path.is_dir() {
+        Ok(path)
+    } else {
+        Err(format!("`{}` is not a directory", dir))
+    }
+}
+
 pub fn get_global_config_path() -> PathBuf {
     home_dir()
         .unwrap()
@@ -138,8 +420,61 @@ pub fn get_local_config_path() -> PathBuf {
     path.to_owned()
 }
 
+pub fn get_global_templates_path() -> PathBuf {
+    home_dir()
+        .unwrap()
+        .join(".config")
+        .join(".feature_generator")
+        .join("templates")
+}
+
+pub fn get_local_templates_path() -> PathBuf {
+    let path = Path::new(".feature_generator/templates");
+    path.to_owned()
+}
+
+pub fn get_manifest_path() -> PathBuf {
+    let path = Path::new(".feature_generator/manifest.json");
+    path.to_owned()
+}
+
+pub fn get_history_path() -> PathBuf {
+    let path = Path::new(".feature_generator/history.json");
+    path.to_owned()
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
+
     #[test]
     fn todo() {}
+
+    #[test]
+    fn project_root_is_found_from_modules() {
+        let root = std::env::temp_dir().join("feature_generator_project_root");
+        let _ = std::fs::remove_dir_all(&root);
+        let module = root.join("feature/home/impl");
+        std::fs::create_dir_all(&module).unwrap();
+        std::fs::create_dir_all(root.join("buildSrc")).unwrap();
+
+        assert_eq!(None, find_project_root(&module));
+
+        std::fs::write(root.join("settings.gradle"), "").unwrap();
+        let root = root.canonicalize().unwrap();
+        assert_eq!(Some(root.clone()), find_project_root(&module));
+        assert_eq!(Some(root.clone()), find_project_root(&root));
+
+        std::fs::create_dir_all(root.join("buildSrc/src/main/kotlin")).unwrap();
+        std::fs::write(root.join("buildSrc/settings.gradle.kts"), "").unwrap();
+        assert_eq!(
+            Some(root.clone()),
+            find_project_root(&root.join("buildSrc"))
+        );
+        assert_eq!(
+            Some(root.clone()),
+            find_project_root(&root.join("buildSrc/src/main/kotlin"))
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
 }
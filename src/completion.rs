@@ -0,0 +1,212 @@
+use clap::{Arg, Command};
+use clap_complete::Shell;
+
+use crate::generation::file_system::FileSystem;
+use crate::generation::gen_context::{existing_features, existing_libraries, AppTarget};
+use crate::generation::templates;
+
+const BASH: &str = r#"_feature_generator() {
+    local IFS=$'\n'
+    COMPREPLY=($(feature_generator __complete -- "${COMP_WORDS[@]:1:$COMP_CWORD}"))
+}
+complete -o default -F _feature_generator feature_generator
+"#;
+
+const ZSH: &str = r#"#compdef feature_generator
+_feature_generator() {
+    local -a candidates
+    candidates=("${(@f)$(feature_generator __complete -- "${(@)words[2,CURRENT]}")}")
+    if [[ -n "${candidates[1]}" ]]; then
+        compadd -a candidates
+    else
+        _files
+    fi
+}
+compdef _feature_generator feature_generator
+"#;
+
+const FISH: &str = r#"function __feature_generator_complete
+    set -l tokens (commandline -opc)
+    set -l current (commandline -ct)
+    feature_generator __complete -- $tokens[2..-1] "$current"
+end
+complete -c feature_generator -f -a '(__feature_generator_complete)'
+"#;
+
+/// A completion script asking `__complete` for the candidates, `None` for shells only getting the
+/// static clap completions
+pub fn script(shell: Shell) -> Option<&'static str> {
+    match shell {
+        Shell::Bash => Some(BASH),
+        Shell::Zsh => Some(ZSH),
+        Shell::Fish => Some(FISH),
+        _ => None,
+    }
+}
+
+/// Candidates for the last of `words`, the arguments typed so far without the binary name. Feature
+/// and library names are looked up in `fs`, app names in the config.
+pub fn complete(
+    cli: Command,
+    words: &[String],
+    fs: &dyn FileSystem,
+    apps: &[AppTarget],
+) -> Vec<String> {
+    let (current, typed) = match words.split_last() {
+        Some((current, typed)) => (current.as_str(), typed),
+        None => ("", words),
+    };
+
+    let mut command = cli;
+    command.build();
+    let mut positionals = 0;
+    let mut pending: Option<Arg> = None;
+    for word in typed {
+        if pending.take().is_some() {
+            continue;
+        }
+        if let Some(option) = find_option(&command, word) {
+            if !word.contains('=') && option.get_action().takes_values() {
+                pending = Some(option);
+            }
+        } else if let Some(subcommand) = command.find_subcommand(word) {
+            command = subcommand.clone();
+            positionals = 0;
+        } else {
+            positionals += 1;
+        }
+    }
+
+    let candidates = if let Some(option) = pending {
+        values(&command, &option, fs, apps)
+    } else if current.starts_with('-') {
+        command
+            .get_arguments()
+            .filter(|arg| !arg.is_hide_set())
+            .filter_map(|arg| arg.get_long())
+            .map(|long| format!("--{}", long))
+            .collect()
+    } else if command.has_subcommands() {
+        command
+            .get_subcommands()
+            .filter(|subcommand| !subcommand.is_hide_set())
+            .map(|subcommand| subcommand.get_name().to_string())
+            .collect()
+    } else {
+        match command.get_positionals().nth(positionals) {
+            Some(positional) => values(&command, positional, fs, apps),
+            None => Vec::new(),
+        }
+    };
+
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(current))
+        .collect()
+}
+
+fn find_option(command: &Command, word: &str) -> Option<Arg> {
+    let option = if let Some(long) = word.strip_prefix("--") {
+        let long = long.split('=').next().unwrap_or_default();
+        command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long))
+    } else {
+        let short = word.strip_prefix('-')?.chars().next()?;
+        command
+            .get_arguments()
+            .find(|arg| arg.get_short() == Some(short))
+    };
+    option.cloned()
+}
+
+/// Values of `arg`. Only existing modules are offered, names of new ones are left to the user.
+fn values(command: &Command, arg: &Arg, fs: &dyn FileSystem, apps: &[AppTarget]) -> Vec<String> {
+    let possible_values = arg.get_possible_values();
+    if !possible_values.is_empty() {
+        return possible_values
+            .iter()
+            .filter(|value| !value.is_hide_set())
+            .map(|value| value.get_name().to_string())
+            .collect();
+    }
+
+    let id = arg.get_id().as_str();
+    match (command.get_name(), id) {
+        (_, "feature" | "navigates_to" | "feature_api") if !arg.is_positional() => {
+            existing_features(fs)
+        }
+        (_, "lib" | "uses_libs") if !arg.is_positional() => existing_libraries(fs),
+        (_, "apps") => apps
+            .iter()
+            .map(|app| app.name.clone())
+            .chain(["all".to_string()])
+            .collect(),
+        ("eject" | "diff", "name") => templates::builtin_sources()
+            .into_iter()
+            .map(|source| source.name)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args_parser::Cli;
+    use crate::generation::file_system::MemoryFileSystem;
+    use clap::CommandFactory;
+    use std::path::PathBuf;
+
+    fn complete_in(fs: &dyn FileSystem, words: &[&str]) -> Vec<String> {
+        let words: Vec<String> = words.iter().map(|word| word.to_string()).collect();
+        let apps = [AppTarget {
+            name: "wear".into(),
+            module: PathBuf::from("wear"),
+            application: None,
+        }];
+        complete(Cli::command(), &words, fs, &apps)
+    }
+
+    #[test]
+    fn complete_subcommands_and_options() {
+        let root = &MemoryFileSystem::new();
+
+        assert_eq!(complete_in(root, &["gen-s"]), vec!["gen-subfeat"]);
+        assert_eq!(complete_in(root, &["templates", "e"]), vec!["eject"]);
+        assert!(!complete_in(root, &[""]).contains(&"__complete".to_string()));
+        assert_eq!(complete_in(root, &["gen-subfeat", "--s"]), vec!["--screen"]);
+        assert_eq!(
+            complete_in(root, &["graph", "--format", ""]),
+            vec!["dot", "mermaid", "json"]
+        );
+        assert_eq!(
+            complete_in(root, &["gen-lib", "saved", "--app", ""]),
+            vec!["wear", "all"]
+        );
+        assert!(complete_in(root, &["templates", "eject", "impl/ImplB"])
+            .contains(&"impl/ImplBuild".to_string()));
+    }
+
+    #[test]
+    fn complete_existing_modules() {
+        let root = MemoryFileSystem::new()
+            .with_file("feature/home/api/build.gradle.kts", "")
+            .with_file("feature/profile/api/build.gradle.kts", "")
+            .with_file("library/saved-faces/api/build.gradle.kts", "");
+
+        assert_eq!(
+            complete_in(&root, &["gen-subfeat", "--feature", "p"]),
+            vec!["profile"]
+        );
+        assert_eq!(
+            complete_in(&root, &["-d", "add-dep", "-f", "home", "--lib", ""]),
+            vec!["saved-faces"]
+        );
+        assert_eq!(
+            complete_in(&root, &["new", "-k", "subfeature", "-f", ""]),
+            vec!["home", "profile"]
+        );
+        assert!(complete_in(&root, &["gen-feat", ""]).is_empty());
+    }
+}
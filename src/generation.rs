@@ -1,4 +1,7 @@
-use std::path::Path;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use crate::args_parser;
 use crate::{args_parser::Cli, helpers};
@@ -8,6 +11,7 @@ use either::Either;
 use handlebars::Handlebars;
 use serde::{Deserialize, Serialize};
 
+use self::file_system::{FileSystem, RealFileSystem};
 use self::gen_context::AppCtx;
 use self::gen_context::AppGenCtx;
 use self::gen_context::Config;
@@ -15,45 +19,72 @@ use self::gen_context::CtxCreationError;
 use self::gen_context::FeatureGenCtx;
 use self::gen_context::GenCtx;
 use self::gen_context::Library;
-use self::render::append_line_below;
-use self::render::{add_line_to_file, overwrite_file_at_path};
-
+use self::gradle::Dsl;
+use self::history::{content_hash, CreatedFile, History, HistoryEntry};
+use self::kotlin::Edit;
+use self::manifest::{GeneratedFile, Manifest};
+use self::render::{overwrite_file_at_path, Insertion};
+use self::templates::TemplateSet;
+use self::templates::{
+    API_BUILD, API_FEATURE_ENTRY, FEATURE_ROOT, GET_EXAMPLE, GET_EXAMPLE_USE_CASE, IMPL_BUILD,
+    LIB_API_BUILD, LIB_DECL, LIB_IMPL_BUILD, LIB_PROVIDER, PAGE_SCREEN, PAGE_VIEW_MODEL,
+    SUBFEATURE,
+};
+
+pub mod dependency;
+pub mod file_system;
 pub mod gen_context;
+pub mod gradle;
+pub mod graph;
+pub mod history;
+pub mod kotlin;
+pub mod listing;
+pub mod manifest;
+pub mod navigation;
 pub mod render;
+pub mod templates;
+pub mod undo;
+pub mod upgrade;
 
 pub use gen_context::Feature;
 pub use gen_context::Subfeature;
 
-const API_FEATURE_ENTRY: &str = include_str!("templates/api/FeatureEntry.handlebars");
-const API_BUILD: &str = include_str!("templates/api/ApiBuild.handlebars");
-const IMPL_BUILD: &str = include_str!("templates/impl/ImplBuild.handlebars");
-const FEATURE_ROOT: &str = include_str!("templates/impl/root/FeatureRoot.handlebars");
-const SUBFEATURE: &str = include_str!("./templates/impl/firstpage/Subfeature.handlebars");
-const PAGE_SCREEN: &str =
-    include_str!("templates/impl/firstpage/screen/FirstPageScreen.handlebars");
-const PAGE_VIEW_MODEL: &str =
-    include_str!("templates/impl/firstpage/screen/FirstPageViewModel.handlebars");
-const LIB_API_BUILD: &str = include_str!("./templates/lib/api/LibApiBuild.handlebars");
-const LIB_PROVIDER: &str = include_str!("./templates/lib/api/Provider.handlebars");
-const GET_EXAMPLE: &str = include_str!("./templates/lib/api/GetExample.handlebars");
-const LIB_IMPL_BUILD: &str = include_str!("./templates/lib/impl/build.gradle.kts.handlebars");
-const LIB_DECL: &str = include_str!("./templates/lib/impl/LibDecl.handlebars");
-const GET_EXAMPLE_USE_CASE: &str =
-    include_str!("./templates/lib/impl/GetExampleUseCase.kt.handlebars");
-
 pub fn register_helpers(handlebars: &mut Handlebars) {
     handlebars.register_helper("flat", Box::new(helpers::to_flat));
     handlebars.register_helper("pascal", Box::new(helpers::to_pascal));
     handlebars.register_helper("camel", Box::new(helpers::to_camel));
     handlebars.register_helper("kebab", Box::new(helpers::to_kebab));
+    handlebars.register_helper("snake", Box::new(helpers::to_snake));
+    handlebars.register_helper("screaming_snake", Box::new(helpers::to_screaming_snake));
+    handlebars.register_helper("title", Box::new(helpers::to_title));
+    handlebars.register_helper("package_path", Box::new(helpers::to_package_path));
+    handlebars.register_helper("plural", Box::new(helpers::to_plural));
+    handlebars.register_helper("singular", Box::new(helpers::to_singular));
+    handlebars.register_helper("concat", Box::new(helpers::concat));
+    handlebars.register_helper("eq", Box::new(helpers::eq));
+    handlebars.register_helper("not", Box::new(helpers::not));
+}
+
+/// Handlebars instance in strict mode, with every helper and every template of `templates`
+/// registered
+pub fn new_handlebars<'a>(templates: &TemplateSet) -> Handlebars<'a> {
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(true);
+    register_helpers(&mut handlebars);
+    templates.register(&mut handlebars);
+    handlebars
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct HandlebarsContext {
     base_package: Option<String>,
     app: Option<String>,
     module: Option<String>,
     first_page: Option<String>,
+    #[serde(default)]
+    used_libraries: Vec<String>,
+    #[serde(default)]
+    navigation_targets: Vec<String>,
 }
 
 impl HandlebarsContext {
@@ -77,12 +108,34 @@ impl HandlebarsContext {
             } else {
                 None
             };
+        let (used_libraries, navigation_targets) = if let GenCtx::App(AppGenCtx::Feature(
+            FeatureGenCtx::Root(root),
+        )) = generation_context
+        {
+            (root.used_libraries.clone(), root.navigation_targets.clone())
+        } else {
+            (Vec::new(), Vec::new())
+        };
 
         Self {
             base_package: base_package.cloned(),
             app: app.cloned(),
             module,
             first_page,
+            used_libraries,
+            navigation_targets,
+        }
+    }
+
+    /// Context with every field filled, used to check that templates render
+    pub fn sample(base_package: Option<String>, app: Option<String>) -> Self {
+        Self {
+            base_package: Some(base_package.unwrap_or_else(|| "com.example".to_string())),
+            app: Some(app.unwrap_or_else(|| "sample-app".to_string())),
+            module: Some("sample-feature".to_string()),
+            first_page: Some("sample-page".to_string()),
+            used_libraries: vec!["sample-library".to_string()],
+            navigation_targets: vec!["sample-target".to_string()],
         }
     }
 }
@@ -92,6 +145,37 @@ pub struct Generator<'a> {
     handlebars: Handlebars<'a>,
     handlebars_context: HandlebarsContext,
     generation_context: GenCtx,
+    /// Where the project is read from and written to
+    fs: Arc<dyn FileSystem>,
+    generated_files: RefCell<Vec<GeneratedFile>>,
+    insertions: RefCell<Vec<Insertion>>,
+    /// Problems which didn't stop the generation, but left something for the user to do
+    warnings: RefCell<Vec<String>>,
+    /// Only collect what would be generated, without writing anything
+    dry_run: bool,
+    /// Content of the files edited during a dry run, so later edits build on earlier ones
+    edited: RefCell<BTreeMap<PathBuf, String>>,
+    /// Version of every template, recorded in the manifest
+    versions: BTreeMap<String, String>,
+}
+
+/// Files a generation creates, and lines it inserts into existing ones
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Plan {
+    pub created: Vec<PathBuf>,
+    pub insertions: Vec<Insertion>,
+    /// Steps which couldn't be done, e.g. registering the module in an application class
+    pub warnings: Vec<String>,
+}
+
+/// A plan together with the content of every file it touches
+#[derive(Debug, PartialEq)]
+pub struct Preview {
+    pub plan: Plan,
+    /// Content of the files the plan creates
+    pub created: BTreeMap<PathBuf, String>,
+    /// Content of the existing files the plan edits, once every insertion is made
+    pub edited: BTreeMap<PathBuf, String>,
 }
 
 impl<'a> Generator<'a> {
@@ -99,26 +183,86 @@ impl<'a> Generator<'a> {
         cli: Cli,
         mut register_helpers: impl FnMut(&mut Handlebars),
     ) -> Result<Self, CtxCreationError> {
+        let fs = Arc::new(cli.file_system());
+        let templates = TemplateSet::load(fs.as_ref());
         let mut handlebars = Handlebars::new();
+        handlebars.set_strict_mode(true);
         register_helpers(&mut handlebars);
-        let generation_context = GenCtx::from_cli(cli.clone())?;
+        templates.register(&mut handlebars);
+        let generation_context = GenCtx::from_cli_in(cli, fs.as_ref())?;
+        Self::checked(generation_context, handlebars, templates.versions(), fs)
+    }
+
+    /// Like `from_cli`, validating and generating the project in `fs` with `templates` read
+    /// beforehand, e.g. once for many generations
+    pub fn from_cli_in(
+        cli: Cli,
+        templates: &TemplateSet,
+        fs: Arc<dyn FileSystem>,
+    ) -> Result<Self, CtxCreationError> {
+        let generation_context = GenCtx::from_cli_in(cli, fs.as_ref())?;
+        Self::checked(
+            generation_context,
+            new_handlebars(templates),
+            templates.versions(),
+            fs,
+        )
+    }
+
+    /// Generator for a context made with `GenCtx::feature`, `GenCtx::subfeature` or
+    /// `GenCtx::library`, writing to `fs`. Fails like `from_cli` when the application class of
+    /// an app can't be found.
+    pub fn for_context(
+        generation_context: GenCtx,
+        fs: Arc<dyn FileSystem>,
+    ) -> Result<Self, CtxCreationError> {
+        let templates = TemplateSet::load(fs.as_ref());
+        Self::checked(
+            generation_context,
+            new_handlebars(&templates),
+            templates.versions(),
+            fs,
+        )
+    }
+
+    fn checked(
+        generation_context: GenCtx,
+        handlebars: Handlebars<'a>,
+        versions: BTreeMap<String, String>,
+        fs: Arc<dyn FileSystem>,
+    ) -> Result<Self, CtxCreationError> {
+        for app in generation_context.app_targets() {
+            app.application_class(fs.as_ref())?;
+        }
         let handlebars_context = HandlebarsContext::new(&generation_context);
 
         Ok(Generator {
             handlebars,
             handlebars_context,
             generation_context,
+            generated_files: RefCell::default(),
+            insertions: RefCell::default(),
+            warnings: RefCell::default(),
+            dry_run: false,
+            edited: RefCell::default(),
+            versions,
+            fs,
         })
     }
 
     pub fn new(generation_context: GenCtx) -> Self {
-        let mut handlebars = Handlebars::new();
-        register_helpers(&mut handlebars);
-
+        let templates = TemplateSet::load(&RealFileSystem);
         Generator {
-            handlebars,
+            handlebars: new_handlebars(&templates),
             handlebars_context: HandlebarsContext::new(&generation_context),
             generation_context,
+            fs: Arc::new(RealFileSystem),
+            generated_files: RefCell::default(),
+            insertions: RefCell::default(),
+            warnings: RefCell::default(),
+            dry_run: false,
+            edited: RefCell::default(),
+            versions: templates.versions(),
         }
     }
 
@@ -126,11 +270,154 @@ impl<'a> Generator<'a> {
         Self::from_cli_internal(cli, &register_helpers)
     }
 
-    pub fn generate(&self) {
-        match &self.generation_context {
-            GenCtx::App(ref app_gen_ctx) => self.generate_app(app_gen_ctx),
-            GenCtx::Config(ref config_ctx) => self.generate_config(config_ctx),
+    /// Reads and writes the project, and its template overrides, through `fs` instead of the
+    /// current directory
+    pub fn with_file_system(mut self, fs: Arc<dyn FileSystem>) -> Self {
+        let templates = TemplateSet::load(fs.as_ref());
+        self.handlebars = new_handlebars(&templates);
+        self.versions = templates.versions();
+        self.fs = fs;
+        self
+    }
+
+    /// What `generate` would do, without writing anything. Templates are still rendered, so
+    /// broken ones panic the same way.
+    pub fn plan(self) -> Plan {
+        self.preview().plan
+    }
+
+    /// Like `plan`, with the content the created and edited files would have
+    pub fn preview(mut self) -> Preview {
+        self.dry_run = true;
+        if let GenCtx::Config(ref config_ctx) = self.generation_context {
+            let path = Self::config_path(config_ctx);
+            return Preview {
+                plan: Plan {
+                    created: vec![path.clone()],
+                    insertions: Vec::new(),
+                    warnings: Vec::new(),
+                },
+                created: BTreeMap::from([(path, Self::config_lines(config_ctx).join("\n") + "\n")]),
+                edited: BTreeMap::new(),
+            };
+        }
+        if let GenCtx::App(ref app_gen_ctx) = self.generation_context {
+            self.generate_app(app_gen_ctx);
+        }
+
+        let generated_files = self.generated_files.take();
+        Preview {
+            plan: Plan {
+                created: generated_files
+                    .iter()
+                    .map(|file| file.path.clone())
+                    .collect(),
+                insertions: self.insertions.take(),
+                warnings: self.warnings.take(),
+            },
+            created: generated_files
+                .into_iter()
+                .map(|file| (file.path, file.content))
+                .collect(),
+            edited: self.edited.take(),
+        }
+    }
+
+    /// Generates the files, returning what was created and inserted
+    pub fn generate(&self) -> Plan {
+        let config_path = match &self.generation_context {
+            GenCtx::App(ref app_gen_ctx) => {
+                self.generate_app(app_gen_ctx);
+                None
+            }
+            GenCtx::Config(ref config_ctx) => {
+                self.generate_config(config_ctx);
+                Some(Self::config_path(config_ctx))
+            }
+        };
+
+        let plan = Plan {
+            created: config_path
+                .into_iter()
+                .chain(
+                    self.generated_files
+                        .borrow()
+                        .iter()
+                        .map(|file| file.path.clone()),
+                )
+                .collect(),
+            insertions: self.insertions.borrow().clone(),
+            warnings: self.warnings.borrow().clone(),
+        };
+        self.save_records();
+        plan
+    }
+
+    /// Stores what this generation created and inserted in the manifest and the history
+    fn save_records(&self) {
+        let generated_files = self.generated_files.take();
+        let insertions = self.insertions.take();
+        if generated_files.is_empty() && insertions.is_empty() {
+            return;
         }
+
+        let created = generated_files
+            .iter()
+            .map(|file| CreatedFile {
+                path: file.path.clone(),
+                hash: content_hash(&file.content),
+            })
+            .collect();
+
+        let manifest_path = args_parser::get_manifest_path();
+        let mut manifest = Manifest::load_from(self.fs.as_ref(), &manifest_path);
+        for file in generated_files {
+            manifest.record(file);
+        }
+        manifest.save_to(self.fs.as_ref(), &manifest_path);
+
+        History::append(
+            self.fs.as_ref(),
+            &args_parser::get_history_path(),
+            HistoryEntry::new(self.generation_context.clone(), created, insertions),
+        );
+    }
+
+    /// Applies `edit` to the file at `path` and records the insertion. When planning, the edit is
+    /// only made to a copy of the file kept in memory.
+    fn edit_file(&self, path: &Path, edit: impl FnOnce(&str) -> Option<Edit>) {
+        if !self.dry_run {
+            self.record_insertion(render::edit_file(self.fs.as_ref(), path, edit));
+            return;
+        }
+
+        let mut edited = self.edited.borrow_mut();
+        let Some(content) = edited
+            .get(path)
+            .cloned()
+            .or_else(|| self.fs.read_to_string(path).ok())
+        else {
+            return;
+        };
+        if let Some((content, insertion)) = render::apply_edit(path, content, edit) {
+            edited.insert(path.to_path_buf(), content);
+            self.record_insertion(Some(insertion));
+        }
+    }
+
+    /// Keeps the line numbers of earlier insertions into the same file up to date
+    fn record_insertion(&self, insertion: Option<Insertion>) {
+        let Some(insertion) = insertion else {
+            return;
+        };
+
+        let mut insertions = self.insertions.borrow_mut();
+        for earlier in insertions.iter_mut() {
+            if earlier.path == insertion.path && earlier.line >= insertion.line {
+                earlier.line = earlier.line + insertion.lines.len() - insertion.replaced.len();
+            }
+        }
+        insertions.push(insertion);
     }
 
     fn generate_app(&self, app_gen_ctx: &'a AppGenCtx) {
@@ -159,16 +446,17 @@ impl<'a> Generator<'a> {
             API_FEATURE_ENTRY,
         );
 
+        let dsl = Dsl::of_project(self.fs.as_ref());
         self.generate_file(
             &feature.app_context.feature_api_path(&feature.feature_name),
-            "build.gradle.kts",
-            API_BUILD,
+            dsl.build_file_name(),
+            dsl.build_template(API_BUILD),
         );
 
         self.generate_file(
             &feature.app_context.feature_impl_path(&feature.feature_name),
-            "build.gradle.kts",
-            IMPL_BUILD,
+            dsl.build_file_name(),
+            dsl.build_template(IMPL_BUILD),
         );
 
         self.generate_file(
@@ -190,16 +478,15 @@ impl<'a> Generator<'a> {
     }
 
     fn amend_existing_files(&self, feat_or_lib: Either<&Feature, &Library>) {
-        let (app_ctx, scaffold_line, scaffold_decl, import, name, mod_type) = match feat_or_lib {
+        let (scaffold_argument, scaffold_entry, import, name, mod_type) = match feat_or_lib {
             Either::Left(feature) => (
-                &feature.app_context,
-                "features =",
+                "features",
                 format!(
-                    "        {}FeatureRoot::class,",
+                    "{}FeatureRoot::class",
                     feature.feature_name.to_case(Case::Pascal)
                 ),
                 format!(
-                    "import {}.{}.impl.root.{}FeatureRoot",
+                    "{}.{}.impl.root.{}FeatureRoot",
                     feature.app_context.base_package,
                     feature.feature_name.to_case(Case::Flat),
                     feature.feature_name.to_case(Case::Pascal),
@@ -208,14 +495,10 @@ impl<'a> Generator<'a> {
                 "feature",
             ),
             Either::Right(library) => (
-                &library.app_context,
-                "libraries =",
-                format!(
-                    "        {}::class,",
-                    library.library_name.to_case(Case::Pascal)
-                ),
+                "libraries",
+                format!("{}::class", library.library_name.to_case(Case::Pascal)),
                 format!(
-                    "import {}.{}.impl.{}",
+                    "{}.{}.impl.{}",
                     library.app_context.base_package,
                     library.library_name.to_case(Case::Flat),
                     library.library_name.to_case(Case::Pascal),
@@ -224,50 +507,107 @@ impl<'a> Generator<'a> {
                 "library",
             ),
         };
+        let apps = self.generation_context.app_targets();
+        for app in &apps {
+            let application_class =
+                app.application_class(self.fs.as_ref())
+                    .unwrap_or_else(|error| {
+                        panic!(
+                            "Couldn't find the application class of `{}`: {:?}",
+                            app.name, error
+                        )
+                    });
+
+            let mut registered = true;
+            self.edit_file(&application_class, |source| {
+                let edit =
+                    kotlin::add_array_entry(source, "Scaffold", scaffold_argument, &scaffold_entry);
+                registered = edit.is_some();
+                edit
+            });
+            if !registered {
+                self.warnings.borrow_mut().push(format!(
+                    "`{}` isn't registered in {}, its `@Scaffold` has no `{} = [...]` argument, add `{}` to it",
+                    name,
+                    application_class.display(),
+                    scaffold_argument,
+                    scaffold_entry
+                ));
+            }
+
+            self.edit_file(&application_class, |source| {
+                Some(kotlin::add_import(source, &import))
+            });
+        }
 
-        let application_class = self.application_root(app_ctx).join("Application.kt");
-
-        append_line_below(&application_class, scaffold_line, &scaffold_decl);
-
-        append_line_below(&application_class, "import ", &import);
-
-        add_line_to_file(
-            Path::new("settings.gradle.kts"),
-            &format!(
-                "include(\":{0}:{1}:api\")\ninclude(\":{0}:{1}:impl\")",
-                mod_type, name
-            ),
-        );
+        // Feature directories are kebab-case, so are their project paths
+        let project = match feat_or_lib {
+            Either::Left(_) => name.to_case(Case::Kebab),
+            Either::Right(_) => name.to_string(),
+        };
+        let api_project = format!(":{}:{}:api", mod_type, project);
+        let impl_project = format!(":{}:{}:impl", mod_type, project);
+        let (settings_dsl, settings) =
+            Dsl::detect(self.fs.as_ref(), Path::new(""), "settings.gradle");
+        let includes = [
+            settings_dsl.include(&api_project),
+            settings_dsl.include(&impl_project),
+        ];
+        self.edit_file(&settings, |source| {
+            kotlin::add_grouped_lines(source, settings_dsl.include_prefix(), &includes, |line| {
+                line
+            })
+            .or_else(|| Some(kotlin::append_lines(source, &includes)))
+        });
 
-        add_line_to_file(
-            &Self::build_src_path().join("app-modules.kt"),
-            &format!(
+        let app_builds: Vec<_> = apps
+            .iter()
+            .map(|app| Dsl::detect(self.fs.as_ref(), &app.module, "build.gradle"))
+            .collect();
+        let app_modules = Self::build_src_path().join("app-modules.kt");
+        if app_builds.iter().any(|(dsl, _)| *dsl == Dsl::Kotlin) || self.fs.exists(&app_modules) {
+            let accessor = [format!(
                 "val DependencyHandlerScope.{1} get() = createProject(\":{0}:{2}\")",
                 mod_type,
                 name.to_case(Case::Camel),
                 name.to_case(Case::Kebab)
-            ),
-        );
+            )];
+            self.edit_file(&app_modules, |source| {
+                kotlin::add_grouped_lines(
+                    source,
+                    "val DependencyHandlerScope.",
+                    &accessor,
+                    |line| {
+                        line.split_once("createProject(")
+                            .map_or(line, |(_, path)| path)
+                    },
+                )
+                .or_else(|| Some(kotlin::append_lines(source, &accessor)))
+            });
+        }
 
-        append_line_below(
-            Path::new("app/build.gradle.kts"),
-            "dependencies",
-            &format!("    implementation(*{}.all())", name.to_case(Case::Camel)),
-        );
+        for (app_dsl, app_build) in app_builds {
+            // Groovy scripts can't use the accessors of buildSrc
+            let dependencies = match app_dsl {
+                Dsl::Kotlin => vec![format!(
+                    "implementation(*{}.all())",
+                    name.to_case(Case::Camel)
+                )],
+                Dsl::Groovy => vec![
+                    app_dsl.project_dependency("implementation", &api_project),
+                    app_dsl.project_dependency("implementation", &impl_project),
+                ],
+            };
+            self.edit_file(&app_build, |source| {
+                kotlin::add_block_entries(source, "dependencies", &dependencies)
+            });
+        }
     }
 
     fn build_src_path() -> Box<Path> {
         Path::new("buildSrc/src/main/kotlin").into()
     }
 
-    fn application_root(&self, app_ctx: &AppCtx) -> Box<Path> {
-        return Path::new("app")
-            .join("src/main/java")
-            .join(app_ctx.base_package_path_part())
-            .join(app_ctx.app_name.to_case(Case::Flat))
-            .into();
-    }
-
     fn feature_root_path(&self, app_ctx: &AppCtx, feature_name: &str) -> Box<Path> {
         app_ctx
             .feature_impl_package_path(feature_name)
@@ -282,14 +622,37 @@ impl<'a> Generator<'a> {
             .into()
     }
 
-    fn generate_file(&self, path: &Path, file_name: &str, file_content: &str) {
-        render::generate_file(
-            path,
-            &self.handlebars,
-            &self.handlebars_context,
-            file_name,
-            file_content,
-        )
+    fn generate_file(&self, path: &Path, file_name: &str, template_name: &str) {
+        let content = if self.dry_run {
+            render::render_file(
+                path,
+                &self.handlebars,
+                &self.handlebars_context,
+                file_name,
+                template_name,
+            )
+        } else {
+            render::generate_file(
+                self.fs.as_ref(),
+                path,
+                &self.handlebars,
+                &self.handlebars_context,
+                file_name,
+                template_name,
+            )
+        };
+
+        self.generated_files.borrow_mut().push(GeneratedFile {
+            path: path.join(file_name),
+            template: template_name.to_string(),
+            version: self
+                .versions
+                .get(template_name)
+                .cloned()
+                .expect("Generated files should always come from a known template"),
+            context: self.handlebars_context.clone(),
+            content,
+        });
     }
 
     fn generate_subfeature(&self, subfeature: &Subfeature) {
@@ -330,10 +693,11 @@ impl<'a> Generator<'a> {
     }
 
     fn generate_library(&self, library: &Library) {
+        let dsl = Dsl::of_project(self.fs.as_ref());
         self.generate_file(
             &Path::new("library").join(&library.library_name).join("api"),
-            "build.gradle.kts",
-            LIB_API_BUILD,
+            dsl.build_file_name(),
+            dsl.build_template(LIB_API_BUILD),
         );
 
         self.generate_file(
@@ -352,8 +716,8 @@ impl<'a> Generator<'a> {
             &Path::new("library")
                 .join(&library.library_name)
                 .join("impl"),
-            "build.gradle.kts",
-            LIB_IMPL_BUILD,
+            dsl.build_file_name(),
+            dsl.build_template(LIB_IMPL_BUILD),
         );
 
         self.generate_file(
@@ -375,7 +739,7 @@ impl<'a> Generator<'a> {
         Path::new("library")
             .join(&library.library_name)
             .join("api/src/main/kotlin")
-            .join(&library.app_context.base_package_path_part())
+            .join(library.app_context.base_package_path_part())
             .join(library.app_context.app_name.to_case(Case::Flat))
             .join(library.library_name.to_case(Case::Flat))
             .into()
@@ -385,18 +749,29 @@ impl<'a> Generator<'a> {
         Path::new("library")
             .join(&library.library_name)
             .join("impl/src/main/kotlin")
-            .join(&library.app_context.base_package_path_part())
+            .join(library.app_context.base_package_path_part())
             .join(library.library_name.to_case(Case::Flat))
             .join("impl")
             .into()
     }
 
-    fn generate_config(&self, config: &Config) {
-        let config_path = if config.global {
+    fn config_path(config: &Config) -> PathBuf {
+        if config.global {
             args_parser::get_global_config_path()
         } else {
             args_parser::get_local_config_path()
-        };
+        }
+    }
+
+    fn generate_config(&self, config: &Config) {
+        overwrite_file_at_path(
+            self.fs.as_ref(),
+            &Self::config_path(config),
+            Self::config_lines(config),
+        );
+    }
+
+    fn config_lines(config: &Config) -> Vec<String> {
         let mut lines = Vec::new();
         if let Some(package) = config.base_package_name.as_ref() {
             lines.push(format!("base-package = \"{}\"", package));
@@ -404,8 +779,7 @@ impl<'a> Generator<'a> {
         if let Some(name) = config.app_name.as_ref() {
             lines.push(format!("app-name = \"{}\"", name));
         }
-
-        overwrite_file_at_path(&config_path, lines);
+        lines
     }
 }
 
@@ -413,7 +787,9 @@ impl<'a> Generator<'a> {
 mod test {
     use crate::args_parser::Cli;
     use crate::args_parser::Command;
+    use crate::generation::file_system::MemoryFileSystem;
     use crate::generation::gen_context::Config;
+    use crate::output::OutputFormat;
 
     use super::gen_context::GenCtx;
     use super::Generator;
@@ -425,8 +801,14 @@ mod test {
             debug: false,
             base_package: Some("test.base.package".to_string()),
             app_name: Some("test-app-name".to_string()),
+            output: OutputFormat::Text,
+            project_dir: None,
+            app_targets: Vec::new(),
             command: Command::GenFeat {
                 feature: "test-feature".to_string(),
+                apps: Vec::new(),
+                uses_libs: Vec::new(),
+                navigates_to: Vec::new(),
             },
         };
 
@@ -439,6 +821,8 @@ mod test {
                 app: Some("test-app-name".to_string()),
                 module: Some("test-feature".to_string()),
                 first_page: Some("test-feature".to_string()),
+                used_libraries: Vec::new(),
+                navigation_targets: Vec::new(),
             },
             handlebars_context
         )
@@ -450,13 +834,17 @@ mod test {
             debug: false,
             base_package: Some("test.base.package".to_string()),
             app_name: Some("test-app-name".to_string()),
+            output: OutputFormat::Text,
+            project_dir: None,
+            app_targets: Vec::new(),
             command: Command::GenSubfeat {
                 feature: "test-feature".to_string(),
                 screen: "test-subfeature".to_string(),
             },
         };
 
-        let generation_context = GenCtx::from_cli(cli.clone()).unwrap();
+        let fs = MemoryFileSystem::new().with_file("feature/test-feature/build.gradle.kts", "");
+        let generation_context = GenCtx::from_cli_in(cli.clone(), &fs).unwrap();
 
         let handlebars_context = HandlebarsContext::new(&generation_context);
         assert_eq!(
@@ -464,7 +852,9 @@ mod test {
                 base_package: Some("test.base.package".to_string()),
                 app: Some("test-app-name".to_string()),
                 module: Some("test-feature".to_string()),
-                first_page: Some("test-subfeature".to_string())
+                first_page: Some("test-subfeature".to_string()),
+                used_libraries: Vec::new(),
+                navigation_targets: Vec::new(),
             },
             handlebars_context
         )
@@ -482,7 +872,9 @@ mod test {
                 base_package: None,
                 app: None,
                 module: None,
-                first_page: None
+                first_page: None,
+                used_libraries: Vec::new(),
+                navigation_targets: Vec::new(),
             },
             handlebars_context
         )
@@ -494,6 +886,9 @@ mod test {
             debug: false,
             base_package: Some("test.base.package".to_string()),
             app_name: Some("test-app-name".to_string()),
+            output: OutputFormat::Text,
+            project_dir: None,
+            app_targets: Vec::new(),
             command: Command::Config {
                 global: true,
                 base_package: Some("test.base.package".to_string()),
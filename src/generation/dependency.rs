@@ -0,0 +1,187 @@
+use std::path::PathBuf;
+
+use convert_case::{Case, Casing};
+
+use super::file_system::FileSystem;
+use super::gen_context::AppCtx;
+use super::gradle::Dsl;
+use super::kotlin;
+use super::render::{edit_file, Insertion, Removal};
+use crate::args_parser::DependencyArgs;
+
+/// A module an existing feature can depend on, wired in by `add-dep` and out by `remove-dep`
+#[derive(Debug, PartialEq, Clone)]
+pub enum Dependency {
+    /// A library, whose `Provider` also becomes a dependency of the feature root
+    Library(String),
+    /// The api module of another feature, e.g. to navigate into it
+    FeatureApi(String),
+}
+
+impl From<&DependencyArgs> for Dependency {
+    fn from(args: &DependencyArgs) -> Self {
+        match (&args.lib, &args.feature_api) {
+            (Some(library), _) => Dependency::Library(library.clone()),
+            (None, Some(feature)) => Dependency::FeatureApi(feature.clone()),
+            (None, None) => unreachable!("clap requires either --lib or --feature-api"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum DependencyError {
+    UnknownFeature(String),
+    UnknownLibrary(String),
+    /// A file of the feature which has to be edited doesn't exist
+    MissingFile(PathBuf),
+}
+
+impl DependencyError {
+    /// Stable identifier of the error in `--output json` reports
+    pub fn code(&self) -> &'static str {
+        match self {
+            DependencyError::UnknownFeature(_) => "unknown_feature",
+            DependencyError::UnknownLibrary(_) => "unknown_library",
+            DependencyError::MissingFile(_) => "missing_file",
+        }
+    }
+}
+
+impl Dependency {
+    /// Checks everything the edits need before any of them is made, so they happen all or none
+    fn validate(
+        &self,
+        fs: &dyn FileSystem,
+        app_ctx: &AppCtx,
+        feature_name: &str,
+    ) -> Result<(), DependencyError> {
+        if !fs.exists(&app_ctx.feature_impl_path(feature_name)) {
+            return Err(DependencyError::UnknownFeature(feature_name.to_string()));
+        }
+        match self {
+            Dependency::Library(library) if !fs.exists(&app_ctx.library_api_path(library)) => {
+                return Err(DependencyError::UnknownLibrary(library.clone()));
+            }
+            Dependency::FeatureApi(feature) if !fs.exists(&app_ctx.feature_api_path(feature)) => {
+                return Err(DependencyError::UnknownFeature(feature.clone()));
+            }
+            _ => {}
+        }
+
+        let (_, build) = Dsl::detect(fs, &app_ctx.feature_impl_path(feature_name), "build.gradle");
+        let root = self
+            .provider(app_ctx)
+            .map(|_| app_ctx.feature_root_file(feature_name).to_path_buf());
+        match std::iter::once(build)
+            .chain(root)
+            .find(|path| !fs.exists(path))
+        {
+            Some(missing) => Err(DependencyError::MissingFile(missing)),
+            None => Ok(()),
+        }
+    }
+
+    /// The statement of the `dependencies` block of a build script written in `dsl`
+    fn build_dependency(&self, dsl: Dsl) -> String {
+        let (name, project) = match self {
+            Dependency::Library(library) => (library, format!(":library:{}:api", library)),
+            Dependency::FeatureApi(feature) => (
+                feature,
+                format!(":feature:{}:api", feature.to_case(Case::Kebab)),
+            ),
+        };
+        match dsl {
+            Dsl::Kotlin => format!("implementation({}.api())", name.to_case(Case::Camel)),
+            Dsl::Groovy => dsl.project_dependency("implementation", &project),
+        }
+    }
+
+    /// The entry of `@FeatureRoot(dependencies = [...])` and its import, libraries only
+    fn provider(&self, app_ctx: &AppCtx) -> Option<(String, String)> {
+        let Dependency::Library(library) = self else {
+            return None;
+        };
+        let provider = format!("{}Provider", library.to_case(Case::Pascal));
+        Some((
+            format!("{}::class", provider),
+            format!(
+                "{}.{}.{}.{}",
+                app_ctx.base_package.to_case(Case::Flat),
+                app_ctx.app_name.to_case(Case::Flat),
+                library.to_case(Case::Flat),
+                provider
+            ),
+        ))
+    }
+}
+
+/// Adds `dependency` to the impl build script of the feature, and its provider to the feature
+/// root. Whatever is already there is left alone, so it's empty if nothing changed.
+pub fn add_dependency(
+    fs: &dyn FileSystem,
+    app_ctx: &AppCtx,
+    feature_name: &str,
+    dependency: &Dependency,
+) -> Result<Vec<Insertion>, DependencyError> {
+    dependency.validate(fs, app_ctx, feature_name)?;
+    let mut insertions = Vec::new();
+
+    let (dsl, build) = Dsl::detect(fs, &app_ctx.feature_impl_path(feature_name), "build.gradle");
+    let statement = dependency.build_dependency(dsl);
+    insertions.extend(edit_file(fs, &build, |source| {
+        if kotlin::has_block_entry(source, "dependencies", &statement) {
+            return None;
+        }
+        kotlin::add_block_entries(source, "dependencies", std::slice::from_ref(&statement))
+    }));
+
+    if let Some((entry, import)) = dependency.provider(app_ctx) {
+        let root = app_ctx.feature_root_file(feature_name);
+        if let Some(insertion) = edit_file(fs, &root, |source| {
+            if kotlin::has_array_entry(source, "FeatureRoot", "dependencies", &entry) {
+                return None;
+            }
+            kotlin::add_array_entry(source, "FeatureRoot", "dependencies", &entry)
+        }) {
+            insertions.push(insertion);
+            insertions.extend(edit_file(fs, &root, |source| {
+                if kotlin::has_import(source, &import) {
+                    return None;
+                }
+                Some(kotlin::add_import(source, &import))
+            }));
+        }
+    }
+
+    Ok(insertions)
+}
+
+/// Removes what `add_dependency` added, returning the removed lines. Empty if the feature didn't
+/// depend on `dependency`.
+pub fn remove_dependency(
+    fs: &dyn FileSystem,
+    app_ctx: &AppCtx,
+    feature_name: &str,
+    dependency: &Dependency,
+) -> Result<Vec<Removal>, DependencyError> {
+    dependency.validate(fs, app_ctx, feature_name)?;
+    let mut removals = Vec::new();
+
+    let (dsl, build) = Dsl::detect(fs, &app_ctx.feature_impl_path(feature_name), "build.gradle");
+    let statement = dependency.build_dependency(dsl);
+    removals.extend(edit_file(fs, &build, |source| {
+        kotlin::remove_block_entry(source, "dependencies", &statement)
+    }));
+
+    if let Some((entry, import)) = dependency.provider(app_ctx) {
+        let root = app_ctx.feature_root_file(feature_name);
+        removals.extend(edit_file(fs, &root, |source| {
+            kotlin::remove_array_entry(source, "FeatureRoot", "dependencies", &entry)
+        }));
+        removals.extend(edit_file(fs, &root, |source| {
+            kotlin::remove_import(source, &import)
+        }));
+    }
+
+    Ok(removals.into_iter().map(Removal::from).collect())
+}
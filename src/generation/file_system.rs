@@ -0,0 +1,278 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use super::render::write_atomically;
+
+/// Where generation reads the project and its template overrides from, and writes it to. Paths are
+/// relative to the project root, the same way they appear in plans and in the history, except for
+/// the absolute global template directory.
+pub trait FileSystem: Debug + Send + Sync {
+    fn read_to_string(&self, path: &Path) -> io::Result<String>;
+
+    /// Replaces the content of `path`, creating it and its missing parent directories
+    fn write(&self, path: &Path, content: &str) -> io::Result<()>;
+
+    /// Deletes the file at `path`, and the directories it leaves empty
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+    fn exists(&self, path: &Path) -> bool;
+
+    fn is_dir(&self, path: &Path) -> bool;
+
+    /// Paths in `dir` in alphabetical order, none if it can't be read
+    fn read_dir(&self, dir: &Path) -> Vec<PathBuf>;
+}
+
+/// The disk, relative to the current directory
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealFileSystem;
+
+impl FileSystem for RealFileSystem {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    fn write(&self, path: &Path, content: &str) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        write_atomically(path, content)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        remove_with_empty_parents(path, Path::new(""))
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn read_dir(&self, dir: &Path) -> Vec<PathBuf> {
+        let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)
+            .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+            .unwrap_or_default();
+        entries.sort();
+        entries
+    }
+}
+
+/// The disk, relative to `root` instead of the current directory
+#[derive(Debug, Clone)]
+pub struct RootedFileSystem {
+    root: PathBuf,
+}
+
+impl RootedFileSystem {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn resolve(&self, path: &Path) -> PathBuf {
+        self.root.join(path)
+    }
+}
+
+impl FileSystem for RootedFileSystem {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        RealFileSystem.read_to_string(&self.resolve(path))
+    }
+
+    fn write(&self, path: &Path, content: &str) -> io::Result<()> {
+        RealFileSystem.write(&self.resolve(path), content)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        remove_with_empty_parents(&self.resolve(path), &self.root)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.resolve(path).exists()
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.resolve(path).is_dir()
+    }
+
+    fn read_dir(&self, dir: &Path) -> Vec<PathBuf> {
+        RealFileSystem
+            .read_dir(&self.resolve(dir))
+            .into_iter()
+            .map(|entry| match entry.strip_prefix(&self.root) {
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => entry,
+            })
+            .collect()
+    }
+}
+
+/// Removes the file at `path`, then its parent directories up to `root` as long as they're empty
+fn remove_with_empty_parents(path: &Path, root: &Path) -> io::Result<()> {
+    std::fs::remove_file(path)?;
+    let mut parent = path.parent();
+    while let Some(dir) = parent {
+        let is_empty = std::fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_none());
+        if dir == root
+            || dir.as_os_str().is_empty()
+            || !is_empty
+            || std::fs::remove_dir(dir).is_err()
+        {
+            break;
+        }
+        parent = dir.parent();
+    }
+    Ok(())
+}
+
+/// Files kept in memory, for previews and tests. Directories exist as long as they contain a file.
+#[derive(Debug, Default)]
+pub struct MemoryFileSystem {
+    files: Mutex<BTreeMap<PathBuf, String>>,
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the file at `path`, replacing the previous one
+    pub fn with_file(self, path: impl Into<PathBuf>, content: impl Into<String>) -> Self {
+        self.files().insert(path.into(), content.into());
+        self
+    }
+
+    /// Every file, by path
+    pub fn snapshot(&self) -> BTreeMap<PathBuf, String> {
+        self.files().clone()
+    }
+
+    fn files(&self) -> std::sync::MutexGuard<'_, BTreeMap<PathBuf, String>> {
+        // Files are only replaced whole, a panicking writer can't leave one half written
+        self.files
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        self.files().get(path).cloned().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} doesn't exist", path.display()),
+            )
+        })
+    }
+
+    fn write(&self, path: &Path, content: &str) -> io::Result<()> {
+        if self.is_dir(path) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is a directory", path.display()),
+            ));
+        }
+        self.files().insert(path.to_path_buf(), content.to_string());
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        self.files().remove(path).map(|_| ()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} doesn't exist", path.display()),
+            )
+        })
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files().contains_key(path) || self.is_dir(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.files()
+            .keys()
+            .any(|file| file != path && file.starts_with(path))
+    }
+
+    fn read_dir(&self, dir: &Path) -> Vec<PathBuf> {
+        let mut entries: Vec<PathBuf> = self
+            .files()
+            .keys()
+            .filter_map(|file| file.strip_prefix(dir).ok()?.components().next())
+            .map(|name| dir.join(name))
+            .collect();
+        entries.dedup();
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_directories() {
+        let fs = MemoryFileSystem::new()
+            .with_file("feature/home/api/build.gradle.kts", "")
+            .with_file("feature/profile/README.md", "")
+            .with_file("settings.gradle.kts", "include(\":app\")\n");
+
+        assert!(fs.is_dir(Path::new("feature")));
+        assert!(fs.exists(Path::new("feature/home")));
+        assert!(!fs.is_dir(Path::new("settings.gradle.kts")));
+        assert!(!fs.exists(Path::new("feature/hom")));
+        assert_eq!(
+            vec![
+                PathBuf::from("feature/home"),
+                PathBuf::from("feature/profile")
+            ],
+            fs.read_dir(Path::new("feature"))
+        );
+        assert_eq!(
+            vec![
+                PathBuf::from("feature"),
+                PathBuf::from("settings.gradle.kts")
+            ],
+            fs.read_dir(Path::new(""))
+        );
+        assert!(fs.write(Path::new("feature/home"), "").is_err());
+    }
+
+    #[test]
+    fn rooted_paths_stay_relative() {
+        let root = std::env::temp_dir().join("feature_generator_rooted");
+        let _ = std::fs::remove_dir_all(&root);
+        let fs = RootedFileSystem::new(&root);
+
+        fs.write(Path::new("feature/home/api/build.gradle.kts"), "api")
+            .unwrap();
+
+        assert_eq!(
+            "api",
+            std::fs::read_to_string(root.join("feature/home/api/build.gradle.kts")).unwrap()
+        );
+        assert!(fs.is_dir(Path::new("feature/home")));
+        assert_eq!(
+            vec![PathBuf::from("feature/home")],
+            fs.read_dir(Path::new("feature"))
+        );
+
+        fs.remove_file(Path::new("feature/home/api/build.gradle.kts"))
+            .unwrap();
+        assert!(!root.join("feature").exists());
+        assert!(root.is_dir());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -1,22 +1,88 @@
 use std::{
+    fmt::{self, Display, Formatter},
     path::{Path, PathBuf},
-    process::exit,
 };
 
 use convert_case::{Case, Casing};
+use serde::{Deserialize, Serialize};
 
+use super::file_system::FileSystem;
+use super::kotlin;
 use crate::args_parser::{
     Cli,
     Command::{self},
 };
 
-#[derive(Debug, PartialEq)]
+pub mod builder;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(rename_all = "snake_case")]
 pub enum GenCtx {
     App(AppGenCtx),
     Config(Config),
 }
 
 impl GenCtx {
+    /// The command line which would produce this context
+    pub fn command_line(&self) -> String {
+        match self {
+            GenCtx::App(AppGenCtx::Feature(FeatureGenCtx::Root(feature))) => format!(
+                "gen-feat {}{}{}{}",
+                feature.feature_name,
+                app_flags(&feature.apps),
+                feature
+                    .used_libraries
+                    .iter()
+                    .map(|library| format!(" --uses-lib {}", library))
+                    .collect::<String>(),
+                feature
+                    .navigation_targets
+                    .iter()
+                    .map(|target| format!(" --navigates-to {}", target))
+                    .collect::<String>(),
+            ),
+            GenCtx::App(AppGenCtx::Feature(FeatureGenCtx::Subfeature(subfeature))) => format!(
+                "gen-subfeat --feature {} --screen {}",
+                subfeature.feature_name, subfeature.subfeature_name
+            ),
+            GenCtx::App(AppGenCtx::Library(library)) => format!(
+                "gen-lib {}{}",
+                library.library_name,
+                app_flags(&library.apps)
+            ),
+            GenCtx::Config(config) => {
+                let mut command = "config".to_string();
+                if config.global {
+                    command.push_str(" --global");
+                }
+                if let Some(base_package) = &config.base_package_name {
+                    command.push_str(&format!(" --base-package {}", base_package));
+                }
+                if let Some(app_name) = &config.app_name {
+                    command.push_str(&format!(" --app-name {}", app_name));
+                }
+                command
+            }
+        }
+    }
+
+    /// Applications a new feature or library gets registered into, the `app` module if none was
+    /// selected
+    pub fn app_targets(&self) -> Vec<AppTarget> {
+        let (app_ctx, apps) = match self {
+            GenCtx::App(AppGenCtx::Feature(FeatureGenCtx::Root(feature))) => {
+                (&feature.app_context, &feature.apps)
+            }
+            GenCtx::App(AppGenCtx::Library(library)) => (&library.app_context, &library.apps),
+            _ => return Vec::new(),
+        };
+        if apps.is_empty() {
+            vec![app_ctx.default_app_target()]
+        } else {
+            apps.clone()
+        }
+    }
+
     pub fn app_ctx(&self) -> Option<&AppCtx> {
         match self {
             GenCtx::App(app) => Some(app.app_ctx()),
@@ -25,7 +91,35 @@ impl GenCtx {
     }
 }
 
-#[derive(Debug, PartialEq)]
+fn app_flags(apps: &[AppTarget]) -> String {
+    apps.iter()
+        .map(|app| format!(" --app {}", app.name))
+        .collect()
+}
+
+/// The `targets` named in `names`, or every one of them if `all` is among the names
+fn select_apps(
+    targets: &[AppTarget],
+    names: Vec<String>,
+) -> Result<Vec<AppTarget>, CtxCreationError> {
+    if names.iter().any(|name| name == "all") {
+        return Ok(targets.to_vec());
+    }
+
+    names
+        .into_iter()
+        .map(|name| {
+            targets
+                .iter()
+                .find(|target| target.name == name)
+                .cloned()
+                .ok_or(CtxCreationError::UnknownApp(name))
+        })
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(rename_all = "snake_case")]
 pub enum AppGenCtx {
     Feature(FeatureGenCtx),
     Library(Library),
@@ -49,7 +143,8 @@ impl AppGenCtx {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(rename_all = "snake_case")]
 pub enum FeatureGenCtx {
     Root(Feature),
     Subfeature(Subfeature),
@@ -90,9 +185,199 @@ impl FeatureGenCtx {
 pub enum CtxCreationError {
     AppNameMissing,
     BasePackageNameMissing,
+    /// `--app` named an application which isn't declared in the config
+    UnknownApp(String),
+    /// No file in the source sets of the module declares a `@Scaffold` annotated class
+    ApplicationNotFound(PathBuf),
+    /// Several files declare a `@Scaffold` annotated class, the config has to pick one
+    AmbiguousApplication(Vec<PathBuf>),
+    /// `--uses-lib` named a library whose api module doesn't exist
+    UnknownLibrary(String),
+    /// `--navigates-to` named a feature whose api module doesn't exist
+    UnknownFeature(String),
+    /// The name is a Kotlin hard keyword once flattened for package names
+    KeywordName(String),
+    NameStartsWithDigit(String),
+    /// Only letters, digits, `-` and `_` are allowed
+    InvalidNameCharacter(String, char),
+    /// Nothing is left of the name once flattened for package names
+    EmptyName(String),
+    /// A module, or subfeature, with the same flattened name exists at the path
+    NameCollision(String, PathBuf),
+    /// The command, by name, doesn't generate anything
+    NotAGenerationCommand(&'static str),
 }
 
-#[derive(Debug, PartialEq, Clone)]
+impl CtxCreationError {
+    /// Stable identifier of the error in `--output json` reports
+    pub fn code(&self) -> &'static str {
+        match self {
+            CtxCreationError::AppNameMissing => "app_name_missing",
+            CtxCreationError::BasePackageNameMissing => "base_package_missing",
+            CtxCreationError::UnknownApp(_) => "unknown_app",
+            CtxCreationError::ApplicationNotFound(_) => "application_not_found",
+            CtxCreationError::AmbiguousApplication(_) => "ambiguous_application",
+            CtxCreationError::UnknownLibrary(_) => "unknown_library",
+            CtxCreationError::UnknownFeature(_) => "unknown_feature",
+            CtxCreationError::KeywordName(_) => "keyword_name",
+            CtxCreationError::NameStartsWithDigit(_) => "name_starts_with_digit",
+            CtxCreationError::InvalidNameCharacter(_, _) => "invalid_name_character",
+            CtxCreationError::EmptyName(_) => "empty_name",
+            CtxCreationError::NameCollision(_, _) => "name_collision",
+            CtxCreationError::NotAGenerationCommand(_) => "not_a_generation_command",
+        }
+    }
+}
+
+impl Display for CtxCreationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CtxCreationError::AppNameMissing => write!(
+                f,
+                "App name is missing from config, please run `feature_generator config` first"
+            ),
+            CtxCreationError::BasePackageNameMissing => write!(
+                f,
+                "App base package is missing from config, please run `feature_generator config` first"
+            ),
+            CtxCreationError::UnknownApp(name) => write!(
+                f,
+                "App `{}` isn't declared in the config, add an `[apps.{}]` table with its `module` first",
+                name, name
+            ),
+            CtxCreationError::ApplicationNotFound(module) => write!(
+                f,
+                "No `@Scaffold` annotated class found in the source sets of {:?}, set the \
+                `application` of the app in the config",
+                module
+            ),
+            CtxCreationError::AmbiguousApplication(candidates) => write!(
+                f,
+                "Several `@Scaffold` annotated classes found: {:?}, set the `application` of the \
+                app in the config to pick one",
+                candidates
+            ),
+            CtxCreationError::UnknownLibrary(name) => write!(
+                f,
+                "Library `{}` doesn't exist, generate it with `feature_generator gen-lib {}` first",
+                name, name
+            ),
+            CtxCreationError::UnknownFeature(name) => write!(
+                f,
+                "Feature `{}` doesn't exist, generate it with `feature_generator gen-feat {}` first",
+                name, name
+            ),
+            CtxCreationError::KeywordName(name) => write!(
+                f,
+                "`{}` is a Kotlin keyword, so it can't be used in package names, please pick another name",
+                name
+            ),
+            CtxCreationError::NameStartsWithDigit(name) => write!(
+                f,
+                "`{}` starts with a digit, which Kotlin identifiers can't, please pick another name",
+                name
+            ),
+            CtxCreationError::InvalidNameCharacter(name, invalid) => write!(
+                f,
+                "`{}` contains `{}`, names can only contain letters, digits, `-` and `_`",
+                name, invalid
+            ),
+            CtxCreationError::EmptyName(name) => write!(
+                f,
+                "`{}` has no letters or digits, please pick another name",
+                name
+            ),
+            CtxCreationError::NameCollision(name, path) => write!(
+                f,
+                "`{}` collides with {:?}, which has the same name once case is ignored",
+                name, path
+            ),
+            CtxCreationError::NotAGenerationCommand(command) => write!(
+                f,
+                "`{}` doesn't generate anything, so it has no generation context",
+                command
+            ),
+        }
+    }
+}
+
+/// Words Kotlin doesn't allow as identifiers, so neither as package segments
+const KOTLIN_HARD_KEYWORDS: [&str; 28] = [
+    "as",
+    "break",
+    "class",
+    "continue",
+    "do",
+    "else",
+    "false",
+    "for",
+    "fun",
+    "if",
+    "in",
+    "interface",
+    "is",
+    "null",
+    "object",
+    "package",
+    "return",
+    "super",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typealias",
+    "typeof",
+    "val",
+    "var",
+    "when",
+    "while",
+];
+
+/// Checks that `name` turns into valid Kotlin identifiers and package segments in every case
+fn validate_name(name: &str) -> Result<(), CtxCreationError> {
+    if let Some(invalid) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+    {
+        return Err(CtxCreationError::InvalidNameCharacter(
+            name.to_string(),
+            invalid,
+        ));
+    }
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(CtxCreationError::NameStartsWithDigit(name.to_string()));
+    }
+    let flat = name.to_case(Case::Flat);
+    if flat.is_empty() {
+        return Err(CtxCreationError::EmptyName(name.to_string()));
+    }
+    if KOTLIN_HARD_KEYWORDS.contains(&flat.as_str()) {
+        return Err(CtxCreationError::KeywordName(name.to_string()));
+    }
+    Ok(())
+}
+
+/// Fails if a feature, library or core module has the same name as `name` once flattened, like
+/// `MemeSearch` and `meme-search`
+fn ensure_new_module(fs: &dyn FileSystem, name: &str) -> Result<(), CtxCreationError> {
+    let flat = name.to_case(Case::Flat);
+    let existing = ["feature", "library", "core"]
+        .iter()
+        .flat_map(|directory| fs.read_dir(Path::new(directory)))
+        .find(|path| {
+            fs.is_dir(path)
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|existing| existing.to_case(Case::Flat) == flat)
+        });
+    match existing {
+        Some(path) => Err(CtxCreationError::NameCollision(name.to_string(), path)),
+        None => Ok(()),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct AppCtx {
     pub is_testing: bool,
     pub app_name: String,
@@ -122,6 +407,18 @@ impl AppCtx {
         path.into()
     }
 
+    /// The `<Feature>FeatureRoot.kt` file of the feature
+    pub fn feature_root_file(&self, feature_name: &str) -> Box<Path> {
+        let mut path = PathBuf::from(self.feature_impl_package_path(feature_name));
+        path.push("root");
+        path.push(format!(
+            "{}FeatureRoot.kt",
+            feature_name.to_case(Case::Pascal)
+        ));
+
+        path.into()
+    }
+
     pub fn feature_api_path(&self, feature_name: &str) -> Box<Path> {
         let mut path = PathBuf::from(self.feature_path(feature_name));
         path.push("api");
@@ -137,31 +434,132 @@ impl AppCtx {
         path.into()
     }
 
+    pub fn library_api_path(&self, library_name: &str) -> Box<Path> {
+        Path::new("library").join(library_name).join("api").into()
+    }
+
     pub fn base_package_path_part(&self) -> Box<Path> {
         Path::new(&self.base_package.replace('.', "/")).into()
     }
+
+    /// The single `app` module used when no application is selected
+    pub fn default_app_target(&self) -> AppTarget {
+        AppTarget {
+            name: self.app_name.clone(),
+            module: PathBuf::from("app"),
+            application: None,
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
+/// An application module features and libraries are registered into, declared in the config as
+///
+/// ```toml
+/// [apps.tv]
+/// module = "tv"
+/// application = "tv/src/main/java/com/my/app/tv/TvApplication.kt"
+/// ```
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct AppTarget {
+    pub name: String,
+    /// Path of the gradle module, e.g. `app`
+    pub module: PathBuf,
+    /// Path of the file declaring the `@Scaffold` annotated application class. When missing, it's
+    /// searched for in the source sets of the module
+    pub application: Option<PathBuf>,
+}
+
+impl AppTarget {
+    /// The configured application file, or the single Kotlin file under
+    /// `<module>/src/<source set>/{java,kotlin}` declaring a `@Scaffold` annotated class
+    pub fn application_class(&self, fs: &dyn FileSystem) -> Result<PathBuf, CtxCreationError> {
+        if let Some(application) = &self.application {
+            return Ok(application.clone());
+        }
+
+        let mut sources = Vec::new();
+        for source_set in fs.read_dir(&self.module.join("src")) {
+            for root in ["java", "kotlin"] {
+                kotlin_files(fs, &source_set.join(root), &mut sources);
+            }
+        }
+        let mut candidates: Vec<PathBuf> = sources
+            .into_iter()
+            .filter(|path| {
+                fs.read_to_string(path)
+                    .is_ok_and(|source| kotlin::has_annotation(&source, "Scaffold"))
+            })
+            .collect();
+
+        match candidates.len() {
+            0 => Err(CtxCreationError::ApplicationNotFound(self.module.clone())),
+            1 => Ok(candidates.remove(0)),
+            _ => Err(CtxCreationError::AmbiguousApplication(candidates)),
+        }
+    }
+}
+
+/// Names of the feature modules in the `feature` directory of the project
+pub fn existing_features(fs: &dyn FileSystem) -> Vec<String> {
+    module_names(fs, Path::new("feature"))
+}
+
+/// Names of the library modules in the `library` directory of the project
+pub fn existing_libraries(fs: &dyn FileSystem) -> Vec<String> {
+    module_names(fs, Path::new("library"))
+}
+
+fn module_names(fs: &dyn FileSystem, dir: &Path) -> Vec<String> {
+    fs.read_dir(dir)
+        .into_iter()
+        .filter(|path| fs.is_dir(path))
+        .filter_map(|path| Some(path.file_name()?.to_str()?.to_string()))
+        .collect()
+}
+
+/// Collects the `.kt` files in `dir` and its subdirectories
+pub(crate) fn kotlin_files(fs: &dyn FileSystem, dir: &Path, files: &mut Vec<PathBuf>) {
+    for path in fs.read_dir(dir) {
+        if fs.is_dir(&path) {
+            kotlin_files(fs, &path, files);
+        } else if path.extension().is_some_and(|extension| extension == "kt") {
+            files.push(path);
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Feature {
     pub app_context: AppCtx,
     pub feature_name: String,
+    /// Applications to register into, the default `app` module when empty
+    #[serde(default)]
+    pub apps: Vec<AppTarget>,
+    /// Libraries the feature depends on, their providers are added to the feature root
+    #[serde(default)]
+    pub used_libraries: Vec<String>,
+    /// Features whose API the feature depends on
+    #[serde(default)]
+    pub navigation_targets: Vec<String>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Subfeature {
     pub app_context: AppCtx,
     pub feature_name: String,
     pub subfeature_name: String,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Library {
     pub app_context: AppCtx,
     pub library_name: String,
+    /// Applications to register into, the default `app` module when empty
+    #[serde(default)]
+    pub apps: Vec<AppTarget>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct Config {
     pub app_name: Option<String>,
     pub base_package_name: Option<String>,
@@ -212,6 +610,12 @@ impl GenCtx {
 
 impl GenCtx {
     pub fn from_cli(cli: Cli) -> Result<Self, CtxCreationError> {
+        let fs = cli.file_system();
+        Self::from_cli_in(cli, &fs)
+    }
+
+    /// Like `from_cli`, checking the existing modules through `fs`
+    pub fn from_cli_in(cli: Cli, fs: &dyn FileSystem) -> Result<Self, CtxCreationError> {
         let app_context = AppCtx {
             app_name: cli.app_name.ok_or(CtxCreationError::AppNameMissing)?,
             base_package: cli
@@ -220,29 +624,47 @@ impl GenCtx {
             is_testing: cli.debug,
         };
 
+        let command = cli.command.name();
         match cli.command {
-            Command::GenerateCompletion { shell: _ } => exit(-1),
-            Command::GenFeat { feature } => {
-                let feature = Feature {
-                    app_context,
-                    feature_name: feature,
-                };
-                Ok(GenCtx::from(feature))
-            }
-            Command::GenSubfeat { feature, screen } => {
-                let subfeature = Subfeature {
-                    app_context,
-                    feature_name: feature,
-                    subfeature_name: screen,
-                };
-                Ok(GenCtx::from(subfeature))
+            Command::GenerateCompletion { shell: _ }
+            | Command::Templates { .. }
+            | Command::Upgrade { .. }
+            | Command::Undo
+            | Command::Graph { .. }
+            | Command::NavMap { .. }
+            | Command::AddDep { .. }
+            | Command::RemoveDep { .. }
+            | Command::New { .. }
+            | Command::Complete { .. }
+            | Command::List
+            | Command::Serve { .. } => Err(CtxCreationError::NotAGenerationCommand(command)),
+            Command::GenFeat {
+                feature,
+                apps,
+                uses_libs,
+                navigates_to,
+            } => {
+                let mut builder = GenCtx::feature(feature).app(app_context);
+                for library in uses_libs {
+                    builder = builder.uses_lib(library);
+                }
+                for target in navigates_to {
+                    builder = builder.navigates_to(target);
+                }
+                for app in select_apps(&cli.app_targets, apps)? {
+                    builder = builder.app_target(app);
+                }
+                builder.build_in(fs)
             }
-            Command::GenLib { lib } => {
-                let library = Library {
-                    app_context,
-                    library_name: lib,
-                };
-                Ok(GenCtx::from(library))
+            Command::GenSubfeat { feature, screen } => GenCtx::subfeature(feature, screen)
+                .app(app_context)
+                .build_in(fs),
+            Command::GenLib { lib, apps } => {
+                let mut builder = GenCtx::library(lib).app(app_context);
+                for app in select_apps(&cli.app_targets, apps)? {
+                    builder = builder.app_target(app);
+                }
+                builder.build_in(fs)
             }
             Command::Config {
                 global,
@@ -264,6 +686,8 @@ impl GenCtx {
 mod tests {
     use super::*;
     use crate::args_parser::{Cli, Command};
+    use crate::generation::file_system::{MemoryFileSystem, RealFileSystem, RootedFileSystem};
+    use crate::output::OutputFormat;
 
     #[test]
     fn app_name_missing() {
@@ -271,22 +695,52 @@ mod tests {
             debug: false,
             base_package: Some("test.base.package".into()),
             app_name: None,
+            output: OutputFormat::Text,
+            project_dir: None,
+            app_targets: Vec::new(),
             command: Command::GenFeat {
                 feature: "test_feature".into(),
+                apps: Vec::new(),
+                uses_libs: Vec::new(),
+                navigates_to: Vec::new(),
             },
         };
 
         assert_eq!(Err(CtxCreationError::AppNameMissing), GenCtx::from_cli(cli))
     }
 
+    #[test]
+    fn not_a_generation_command() {
+        let cli = Cli {
+            debug: false,
+            base_package: Some("test.base.package".into()),
+            app_name: Some("test_app_name".into()),
+            output: OutputFormat::Text,
+            project_dir: None,
+            app_targets: Vec::new(),
+            command: Command::List,
+        };
+
+        assert_eq!(
+            Err(CtxCreationError::NotAGenerationCommand("list")),
+            GenCtx::from_cli(cli)
+        )
+    }
+
     #[test]
     fn base_package_missing() {
         let cli = Cli {
             debug: false,
             base_package: None,
             app_name: Some("test_app_name".into()),
+            output: OutputFormat::Text,
+            project_dir: None,
+            app_targets: Vec::new(),
             command: Command::GenFeat {
                 feature: "test_feature".into(),
+                apps: Vec::new(),
+                uses_libs: Vec::new(),
+                navigates_to: Vec::new(),
             },
         };
 
@@ -300,25 +754,173 @@ mod tests {
     fn generate_feature() {
         let cli = Cli::with_command(Command::GenFeat {
             feature: "test_feature".into(),
+            apps: Vec::new(),
+            uses_libs: Vec::new(),
+            navigates_to: Vec::new(),
         });
 
         assert_eq!(
             Ok(Feature {
                 app_context: AppCtx::default(),
                 feature_name: "test_feature".into(),
+                apps: Vec::new(),
+                used_libraries: Vec::new(),
+                navigation_targets: Vec::new(),
             }
             .into()),
             GenCtx::from_cli(cli)
         );
     }
 
+    #[test]
+    fn generate_feature_for_apps() {
+        let mut cli = Cli::with_command(Command::GenFeat {
+            feature: "test_feature".into(),
+            apps: vec!["tv".into()],
+            uses_libs: Vec::new(),
+            navigates_to: Vec::new(),
+        });
+        cli.app_targets = vec![app_target("phone"), app_target("tv")];
+
+        let ctx = GenCtx::from_cli(cli.clone()).unwrap();
+        assert_eq!("gen-feat test_feature --app tv", ctx.command_line());
+
+        cli.command = Command::GenFeat {
+            feature: "test_feature".into(),
+            apps: vec!["all".into()],
+            uses_libs: Vec::new(),
+            navigates_to: Vec::new(),
+        };
+        assert_eq!(
+            Ok(Feature {
+                app_context: AppCtx::default(),
+                feature_name: "test_feature".into(),
+                apps: vec![app_target("phone"), app_target("tv")],
+                used_libraries: Vec::new(),
+                navigation_targets: Vec::new(),
+            }
+            .into()),
+            GenCtx::from_cli(cli.clone())
+        );
+
+        cli.command = Command::GenLib {
+            lib: "test_lib".into(),
+            apps: vec!["watch".into()],
+        };
+        assert_eq!(
+            Err(CtxCreationError::UnknownApp("watch".into())),
+            GenCtx::from_cli(cli)
+        );
+    }
+
+    #[test]
+    fn invalid_names() {
+        let result = |name: &str| {
+            GenCtx::from_cli(Cli::with_command(Command::GenLib {
+                lib: name.into(),
+                apps: Vec::new(),
+            }))
+        };
+
+        assert_eq!(
+            Err(CtxCreationError::KeywordName("class".into())),
+            result("class")
+        );
+        assert_eq!(
+            Err(CtxCreationError::KeywordName("Typealias".into())),
+            result("Typealias")
+        );
+        assert_eq!(
+            Err(CtxCreationError::NameStartsWithDigit("2fa".into())),
+            result("2fa")
+        );
+        assert_eq!(
+            Err(CtxCreationError::InvalidNameCharacter(
+                "my feature!".into(),
+                ' '
+            )),
+            result("my feature!")
+        );
+        assert_eq!(Err(CtxCreationError::EmptyName("--".into())), result("--"));
+        assert!(result("meme-search2").is_ok());
+    }
+
+    #[test]
+    fn colliding_names() {
+        let existing = Path::new("core/meme-search");
+        let fs = MemoryFileSystem::new().with_file(existing.join("build.gradle.kts"), "");
+
+        let result = GenCtx::from_cli_in(
+            Cli::with_command(Command::GenFeat {
+                feature: "MemeSearch".into(),
+                apps: Vec::new(),
+                uses_libs: Vec::new(),
+                navigates_to: Vec::new(),
+            }),
+            &fs,
+        );
+
+        assert_eq!(
+            Err(CtxCreationError::NameCollision(
+                "MemeSearch".into(),
+                existing.to_path_buf()
+            )),
+            result
+        );
+    }
+
+    #[test]
+    fn generate_feature_with_dependencies() {
+        let mut cli = Cli::with_command(Command::GenFeat {
+            feature: "test_feature".into(),
+            apps: Vec::new(),
+            uses_libs: vec!["missing-lib".into()],
+            navigates_to: Vec::new(),
+        });
+        assert_eq!(
+            Err(CtxCreationError::UnknownLibrary("missing-lib".into())),
+            GenCtx::from_cli(cli.clone())
+        );
+
+        cli.command = Command::GenFeat {
+            feature: "test_feature".into(),
+            apps: Vec::new(),
+            uses_libs: Vec::new(),
+            navigates_to: vec!["missing-feature".into()],
+        };
+        assert_eq!(
+            Err(CtxCreationError::UnknownFeature("missing-feature".into())),
+            GenCtx::from_cli(cli)
+        );
+
+        let feature: GenCtx = Feature {
+            app_context: AppCtx::default(),
+            feature_name: "home".into(),
+            apps: Vec::new(),
+            used_libraries: vec!["saved-faces".into()],
+            navigation_targets: vec!["meme-details".into()],
+        }
+        .into();
+        assert_eq!(
+            "gen-feat home --uses-lib saved-faces --navigates-to meme-details",
+            feature.command_line()
+        );
+    }
+
     #[test]
     fn generate_subfeature() {
         let cli = Cli::with_command(Command::GenSubfeat {
             feature: "test_feature".into(),
             screen: "test_subfeature".into(),
         });
+        let fs = MemoryFileSystem::new();
 
+        assert_eq!(
+            Err(CtxCreationError::UnknownFeature("test_feature".into())),
+            GenCtx::from_cli_in(cli.clone(), &fs)
+        );
+
+        let fs = fs.with_file("feature/test-feature/build.gradle.kts", "");
         assert_eq!(
             Ok(Subfeature {
                 app_context: AppCtx::default(),
@@ -326,7 +928,7 @@ mod tests {
                 subfeature_name: "test_subfeature".into(),
             }
             .into()),
-            GenCtx::from_cli(cli)
+            GenCtx::from_cli_in(cli, &fs)
         )
     }
 
@@ -409,11 +1011,29 @@ mod tests {
         )
     }
 
+    #[test]
+    fn command_line() {
+        let subfeature: GenCtx = Subfeature {
+            app_context: AppCtx::default(),
+            feature_name: "home".into(),
+            subfeature_name: "home-details".into(),
+        }
+        .into();
+
+        assert_eq!(
+            "gen-subfeat --feature home --screen home-details",
+            subfeature.command_line()
+        );
+    }
+
     #[test]
     fn feature_name() {
         let feature = Feature {
             app_context: AppCtx::default(),
             feature_name: "test-feature".into(),
+            apps: Vec::new(),
+            used_libraries: Vec::new(),
+            navigation_targets: Vec::new(),
         };
 
         let root = FeatureGenCtx::Root(feature);
@@ -421,6 +1041,82 @@ mod tests {
         assert_eq!("test-feature", root.feature_name())
     }
 
+    #[test]
+    fn existing_modules_are_directories() {
+        let root = std::env::temp_dir().join("feature_generator_existing_modules");
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("feature/profile")).unwrap();
+        std::fs::create_dir_all(root.join("feature/home")).unwrap();
+        std::fs::write(root.join("feature/README.md"), "").unwrap();
+        std::fs::create_dir_all(root.join("library/saved-faces")).unwrap();
+
+        let fs = RootedFileSystem::new(&root);
+        assert_eq!(existing_features(&fs), vec!["home", "profile"]);
+        assert_eq!(existing_libraries(&fs), vec!["saved-faces"]);
+        let missing = RootedFileSystem::new(root.join("missing"));
+        assert!(existing_features(&missing).is_empty());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn application_class_is_searched_in_source_sets() {
+        let module = std::env::temp_dir().join("feature_generator_application");
+        let _ = std::fs::remove_dir_all(&module);
+        let sources = module.join("src/main/kotlin/com/fresh");
+        std::fs::create_dir_all(&sources).unwrap();
+        std::fs::write(sources.join("MainActivity.kt"), "// @Scaffold\nclass A\n").unwrap();
+        let target = AppTarget {
+            name: "fresh".into(),
+            module: module.clone(),
+            application: None,
+        };
+
+        assert_eq!(
+            Err(CtxCreationError::ApplicationNotFound(module.clone())),
+            target.application_class(&RealFileSystem)
+        );
+
+        std::fs::write(
+            sources.join("FreshApp.kt"),
+            "@Scaffold(features = [])\nclass FreshApp : Application()\n",
+        )
+        .unwrap();
+        assert_eq!(
+            Ok(sources.join("FreshApp.kt")),
+            target.application_class(&RealFileSystem)
+        );
+
+        let debug = module.join("src/debug/java");
+        std::fs::create_dir_all(&debug).unwrap();
+        std::fs::write(debug.join("DebugApp.kt"), "@Scaffold\nclass DebugApp\n").unwrap();
+        assert_eq!(
+            Err(CtxCreationError::AmbiguousApplication(vec![
+                debug.join("DebugApp.kt"),
+                sources.join("FreshApp.kt"),
+            ])),
+            target.application_class(&RealFileSystem)
+        );
+
+        let configured = AppTarget {
+            application: Some(debug.join("DebugApp.kt")),
+            ..target
+        };
+        assert_eq!(
+            Ok(debug.join("DebugApp.kt")),
+            configured.application_class(&RealFileSystem)
+        );
+        std::fs::remove_dir_all(module).unwrap();
+    }
+
+    fn app_target(name: &str) -> AppTarget {
+        AppTarget {
+            name: name.into(),
+            module: PathBuf::from(name),
+            application: None,
+        }
+    }
+
     impl Default for AppCtx {
         fn default() -> Self {
             Self {
@@ -437,6 +1133,9 @@ mod tests {
                 debug: false,
                 base_package: Some("test.base.package".into()),
                 app_name: Some("test_app_name".into()),
+                output: OutputFormat::Text,
+                project_dir: None,
+                app_targets: Vec::new(),
                 command,
             }
         }
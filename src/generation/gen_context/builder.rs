@@ -0,0 +1,332 @@
+use convert_case::{Case, Casing};
+
+use super::{
+    ensure_new_module, validate_name, AppCtx, AppTarget, CtxCreationError, Feature, GenCtx,
+    Library, Subfeature,
+};
+use crate::generation::file_system::{FileSystem, RealFileSystem};
+
+impl GenCtx {
+    /// Starts building the context of a new feature module, e.g.
+    ///
+    /// ```no_run
+    /// # use feature_generator::generation::gen_context::{AppCtx, GenCtx};
+    /// # fn example(app_ctx: AppCtx) -> Result<(), feature_generator::generation::gen_context::CtxCreationError> {
+    /// let ctx = GenCtx::feature("home")
+    ///     .app(app_ctx)
+    ///     .uses_lib("saved-faces")
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn feature(name: impl Into<String>) -> FeatureBuilder {
+        FeatureBuilder {
+            name: name.into(),
+            app: AppParts::default(),
+            apps: Vec::new(),
+            used_libraries: Vec::new(),
+            navigation_targets: Vec::new(),
+        }
+    }
+
+    /// Starts building the context of a new subfeature of the existing `feature`
+    pub fn subfeature(feature: impl Into<String>, name: impl Into<String>) -> SubfeatureBuilder {
+        SubfeatureBuilder {
+            feature: feature.into(),
+            name: name.into(),
+            app: AppParts::default(),
+        }
+    }
+
+    /// Starts building the context of a new library module
+    pub fn library(name: impl Into<String>) -> LibraryBuilder {
+        LibraryBuilder {
+            name: name.into(),
+            app: AppParts::default(),
+            apps: Vec::new(),
+        }
+    }
+}
+
+/// The parts of the `AppCtx`, which can be given one by one
+#[derive(Debug, Clone, Default)]
+struct AppParts {
+    app_name: Option<String>,
+    base_package: Option<String>,
+    is_testing: bool,
+}
+
+impl AppParts {
+    fn from_ctx(app_ctx: AppCtx) -> Self {
+        Self {
+            app_name: Some(app_ctx.app_name),
+            base_package: Some(app_ctx.base_package),
+            is_testing: app_ctx.is_testing,
+        }
+    }
+
+    /// Fails the same way the command line does when the config misses them
+    fn build(self) -> Result<AppCtx, CtxCreationError> {
+        Ok(AppCtx {
+            app_name: self.app_name.ok_or(CtxCreationError::AppNameMissing)?,
+            base_package: self
+                .base_package
+                .ok_or(CtxCreationError::BasePackageNameMissing)?,
+            is_testing: self.is_testing,
+        })
+    }
+}
+
+/// Context of a new feature, see `GenCtx::feature`
+#[derive(Debug, Clone)]
+pub struct FeatureBuilder {
+    name: String,
+    app: AppParts,
+    apps: Vec<AppTarget>,
+    used_libraries: Vec<String>,
+    navigation_targets: Vec<String>,
+}
+
+impl FeatureBuilder {
+    pub fn app(mut self, app_ctx: AppCtx) -> Self {
+        self.app = AppParts::from_ctx(app_ctx);
+        self
+    }
+
+    pub fn app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.app.app_name = Some(app_name.into());
+        self
+    }
+
+    pub fn base_package(mut self, base_package: impl Into<String>) -> Self {
+        self.app.base_package = Some(base_package.into());
+        self
+    }
+
+    /// Application to register the feature into. Can be repeated, the `app` module is used when
+    /// none is given
+    pub fn app_target(mut self, target: AppTarget) -> Self {
+        self.apps.push(target);
+        self
+    }
+
+    /// Existing library the feature depends on and gets provided by. Can be repeated
+    pub fn uses_lib(mut self, library: impl Into<String>) -> Self {
+        self.used_libraries.push(library.into());
+        self
+    }
+
+    /// Existing feature whose API the feature depends on. Can be repeated
+    pub fn navigates_to(mut self, feature: impl Into<String>) -> Self {
+        self.navigation_targets.push(feature.into());
+        self
+    }
+
+    /// Checks the names against the project in the current directory
+    pub fn build(self) -> Result<GenCtx, CtxCreationError> {
+        self.build_in(&RealFileSystem)
+    }
+
+    /// Checks the names against the project in `fs`
+    pub fn build_in(self, fs: &dyn FileSystem) -> Result<GenCtx, CtxCreationError> {
+        let app_context = self.app.build()?;
+        validate_name(&self.name)?;
+        ensure_new_module(fs, &self.name)?;
+        if let Some(library) = self
+            .used_libraries
+            .iter()
+            .find(|library| !fs.exists(&app_context.library_api_path(library)))
+        {
+            return Err(CtxCreationError::UnknownLibrary(library.clone()));
+        }
+        if let Some(target) = self
+            .navigation_targets
+            .iter()
+            .find(|target| !fs.exists(&app_context.feature_api_path(target)))
+        {
+            return Err(CtxCreationError::UnknownFeature(target.clone()));
+        }
+
+        Ok(GenCtx::from(Feature {
+            app_context,
+            feature_name: self.name,
+            apps: self.apps,
+            used_libraries: self.used_libraries,
+            navigation_targets: self.navigation_targets,
+        }))
+    }
+}
+
+/// Context of a new subfeature, see `GenCtx::subfeature`
+#[derive(Debug, Clone)]
+pub struct SubfeatureBuilder {
+    feature: String,
+    name: String,
+    app: AppParts,
+}
+
+impl SubfeatureBuilder {
+    pub fn app(mut self, app_ctx: AppCtx) -> Self {
+        self.app = AppParts::from_ctx(app_ctx);
+        self
+    }
+
+    pub fn app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.app.app_name = Some(app_name.into());
+        self
+    }
+
+    pub fn base_package(mut self, base_package: impl Into<String>) -> Self {
+        self.app.base_package = Some(base_package.into());
+        self
+    }
+
+    /// Checks the names against the project in the current directory
+    pub fn build(self) -> Result<GenCtx, CtxCreationError> {
+        self.build_in(&RealFileSystem)
+    }
+
+    /// Checks the names against the project in `fs`, where the feature has to exist
+    pub fn build_in(self, fs: &dyn FileSystem) -> Result<GenCtx, CtxCreationError> {
+        let app_context = self.app.build()?;
+        validate_name(&self.name)?;
+        if !fs.exists(&app_context.feature_path(&self.feature)) {
+            return Err(CtxCreationError::UnknownFeature(self.feature));
+        }
+        let existing = app_context
+            .feature_impl_package_path(&self.feature)
+            .join("subfeature")
+            .join(self.name.to_case(Case::Flat));
+        if fs.exists(&existing) {
+            return Err(CtxCreationError::NameCollision(self.name, existing));
+        }
+
+        Ok(GenCtx::from(Subfeature {
+            app_context,
+            feature_name: self.feature,
+            subfeature_name: self.name,
+        }))
+    }
+}
+
+/// Context of a new library, see `GenCtx::library`
+#[derive(Debug, Clone)]
+pub struct LibraryBuilder {
+    name: String,
+    app: AppParts,
+    apps: Vec<AppTarget>,
+}
+
+impl LibraryBuilder {
+    pub fn app(mut self, app_ctx: AppCtx) -> Self {
+        self.app = AppParts::from_ctx(app_ctx);
+        self
+    }
+
+    pub fn app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.app.app_name = Some(app_name.into());
+        self
+    }
+
+    pub fn base_package(mut self, base_package: impl Into<String>) -> Self {
+        self.app.base_package = Some(base_package.into());
+        self
+    }
+
+    /// Application to register the library into. Can be repeated, the `app` module is used when
+    /// none is given
+    pub fn app_target(mut self, target: AppTarget) -> Self {
+        self.apps.push(target);
+        self
+    }
+
+    /// Checks the name against the project in the current directory
+    pub fn build(self) -> Result<GenCtx, CtxCreationError> {
+        self.build_in(&RealFileSystem)
+    }
+
+    /// Checks the name against the project in `fs`
+    pub fn build_in(self, fs: &dyn FileSystem) -> Result<GenCtx, CtxCreationError> {
+        let app_context = self.app.build()?;
+        validate_name(&self.name)?;
+        ensure_new_module(fs, &self.name)?;
+
+        Ok(GenCtx::from(Library {
+            app_context,
+            library_name: self.name,
+            apps: self.apps,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::file_system::MemoryFileSystem;
+
+    fn app_ctx() -> AppCtx {
+        AppCtx {
+            is_testing: false,
+            app_name: "test-app".into(),
+            base_package: "test.base.package".into(),
+        }
+    }
+
+    #[test]
+    fn build_feature() {
+        let fs = MemoryFileSystem::new()
+            .with_file("library/saved-faces/api/build.gradle.kts", "")
+            .with_file("feature/meme-details/api/build.gradle.kts", "");
+
+        assert_eq!(
+            Ok(GenCtx::from(Feature {
+                app_context: app_ctx(),
+                feature_name: "home".into(),
+                apps: Vec::new(),
+                used_libraries: vec!["saved-faces".into()],
+                navigation_targets: vec!["meme-details".into()],
+            })),
+            GenCtx::feature("home")
+                .app(app_ctx())
+                .uses_lib("saved-faces")
+                .navigates_to("meme-details")
+                .build_in(&fs)
+        );
+        assert_eq!(
+            Err(CtxCreationError::UnknownLibrary("face-detection".into())),
+            GenCtx::feature("home")
+                .app(app_ctx())
+                .uses_lib("face-detection")
+                .build_in(&fs)
+        );
+        assert_eq!(
+            Err(CtxCreationError::NameCollision(
+                "MemeDetails".into(),
+                "feature/meme-details".into()
+            )),
+            GenCtx::feature("MemeDetails").app(app_ctx()).build_in(&fs)
+        );
+    }
+
+    #[test]
+    fn build_needs_app() {
+        let fs = MemoryFileSystem::new();
+
+        assert_eq!(
+            Err(CtxCreationError::AppNameMissing),
+            GenCtx::library("saved-faces")
+                .base_package("test.base.package")
+                .build_in(&fs)
+        );
+        assert_eq!(
+            Err(CtxCreationError::BasePackageNameMissing),
+            GenCtx::subfeature("home", "details")
+                .app_name("test-app")
+                .build_in(&fs)
+        );
+        assert_eq!(
+            Err(CtxCreationError::KeywordName("class".into())),
+            GenCtx::library("class").app(app_ctx()).build_in(&fs)
+        );
+    }
+}
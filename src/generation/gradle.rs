@@ -0,0 +1,122 @@
+use std::path::{Path, PathBuf};
+
+use super::file_system::FileSystem;
+use super::templates::{
+    API_BUILD, API_BUILD_GROOVY, IMPL_BUILD, IMPL_BUILD_GROOVY, LIB_API_BUILD,
+    LIB_API_BUILD_GROOVY, LIB_IMPL_BUILD, LIB_IMPL_BUILD_GROOVY,
+};
+
+/// Language a gradle script is written in
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Dsl {
+    Kotlin,
+    Groovy,
+}
+
+impl Dsl {
+    /// Finds the `script` (e.g. `settings.gradle`) in `dir`, either as `.kts` or as plain groovy.
+    /// Kotlin if neither exists.
+    pub fn detect(fs: &dyn FileSystem, dir: &Path, script: &str) -> (Self, PathBuf) {
+        let kotlin = dir.join(format!("{}.kts", script));
+        let groovy = dir.join(script);
+        if !fs.exists(&kotlin) && fs.exists(&groovy) {
+            (Self::Groovy, groovy)
+        } else {
+            (Self::Kotlin, kotlin)
+        }
+    }
+
+    /// The DSL of the project, based on its settings script
+    pub fn of_project(fs: &dyn FileSystem) -> Self {
+        Self::detect(fs, Path::new(""), "settings.gradle").0
+    }
+
+    pub fn build_file_name(self) -> &'static str {
+        match self {
+            Self::Kotlin => "build.gradle.kts",
+            Self::Groovy => "build.gradle",
+        }
+    }
+
+    /// Prefix of the lines including modules in the settings script
+    pub fn include_prefix(self) -> &'static str {
+        match self {
+            Self::Kotlin => "include(",
+            Self::Groovy => "include ",
+        }
+    }
+
+    pub fn include(self, project: &str) -> String {
+        match self {
+            Self::Kotlin => format!("include(\"{}\")", project),
+            Self::Groovy => format!("include '{}'", project),
+        }
+    }
+
+    pub fn project_dependency(self, configuration: &str, project: &str) -> String {
+        match self {
+            Self::Kotlin => format!("{}(project(\"{}\"))", configuration, project),
+            Self::Groovy => format!("{} project('{}')", configuration, project),
+        }
+    }
+
+    /// The variant of the built-in module build `template` written in this DSL
+    pub fn build_template(self, template: &'static str) -> &'static str {
+        match (self, template) {
+            (Self::Groovy, API_BUILD) => API_BUILD_GROOVY,
+            (Self::Groovy, IMPL_BUILD) => IMPL_BUILD_GROOVY,
+            (Self::Groovy, LIB_API_BUILD) => LIB_API_BUILD_GROOVY,
+            (Self::Groovy, LIB_IMPL_BUILD) => LIB_IMPL_BUILD_GROOVY,
+            _ => template,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::file_system::RealFileSystem;
+
+    #[test]
+    fn detect_prefers_kotlin() {
+        let dir = std::env::temp_dir().join("feature_generator_gradle");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            (Dsl::Kotlin, dir.join("settings.gradle.kts")),
+            Dsl::detect(&RealFileSystem, &dir, "settings.gradle")
+        );
+
+        std::fs::write(dir.join("settings.gradle"), "").unwrap();
+        assert_eq!(
+            (Dsl::Groovy, dir.join("settings.gradle")),
+            Dsl::detect(&RealFileSystem, &dir, "settings.gradle")
+        );
+
+        std::fs::write(dir.join("settings.gradle.kts"), "").unwrap();
+        assert_eq!(
+            Dsl::Kotlin,
+            Dsl::detect(&RealFileSystem, &dir, "settings.gradle").0
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn syntax() {
+        assert_eq!(
+            "include(\":feature:home:api\")",
+            Dsl::Kotlin.include(":feature:home:api")
+        );
+        assert_eq!(
+            "include ':feature:home:api'",
+            Dsl::Groovy.include(":feature:home:api")
+        );
+        assert_eq!(
+            "implementation project(':feature:home:impl')",
+            Dsl::Groovy.project_dependency("implementation", ":feature:home:impl")
+        );
+        assert_eq!(IMPL_BUILD_GROOVY, Dsl::Groovy.build_template(IMPL_BUILD));
+        assert_eq!(IMPL_BUILD, Dsl::Kotlin.build_template(IMPL_BUILD));
+    }
+}
@@ -0,0 +1,537 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use convert_case::{Case, Casing};
+use serde::Serialize;
+
+use super::file_system::FileSystem;
+use super::gen_context::kotlin_files;
+use super::gradle::Dsl;
+use super::kotlin;
+
+#[derive(Serialize, Debug, PartialEq, Clone, Copy, PartialOrd, Ord, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ModuleKind {
+    Feature,
+    Library,
+}
+
+impl ModuleKind {
+    fn directory(self) -> &'static str {
+        match self {
+            ModuleKind::Feature => "feature",
+            ModuleKind::Library => "library",
+        }
+    }
+}
+
+/// The part of a module depended on
+#[derive(Serialize, Debug, PartialEq, Clone, Copy, PartialOrd, Ord, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    Api,
+    Impl,
+    /// Both the api and the impl, as `*home.all()`
+    All,
+}
+
+/// Where a dependency was found
+#[derive(Serialize, Debug, PartialEq, Clone, Copy, PartialOrd, Ord, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Via {
+    /// The `dependencies` block of the impl build script
+    Build,
+    /// A `Provider` in `@FeatureRoot(dependencies = [...])`
+    Provider,
+}
+
+#[derive(Serialize, Debug, PartialEq, Clone)]
+pub struct Node {
+    pub name: String,
+    pub kind: ModuleKind,
+}
+
+#[derive(Serialize, Debug, PartialEq, Clone, PartialOrd, Ord, Eq)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    pub target: Target,
+    pub via: Via,
+}
+
+impl Edge {
+    /// Impl modules should only ever depend on api modules
+    pub fn breaks_api_split(&self) -> bool {
+        self.target != Target::Api
+    }
+}
+
+/// Dependencies between the feature and library modules of a project
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ModuleGraph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    /// Modules depending on each other, each cycle in alphabetical order
+    pub cycles: Vec<Vec<String>>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+    Json,
+}
+
+impl ModuleGraph {
+    /// Reads the `feature/*/impl` and `library/*/impl` build scripts and feature roots of the
+    /// project in `fs`
+    pub fn load(fs: &dyn FileSystem) -> Self {
+        let nodes: Vec<Node> = [ModuleKind::Feature, ModuleKind::Library]
+            .into_iter()
+            .flat_map(|kind| {
+                fs.read_dir(Path::new(kind.directory()))
+                    .into_iter()
+                    .filter(|path| fs.is_dir(&path.join("impl")))
+                    .filter_map(move |path| {
+                        let name = path.file_name()?.to_str()?.to_string();
+                        Some(Node { name, kind })
+                    })
+            })
+            .collect();
+
+        let accessors: BTreeMap<String, &Node> = nodes
+            .iter()
+            .map(|node| (node.name.to_case(Case::Camel), node))
+            .collect();
+        let providers: BTreeMap<String, &Node> = nodes
+            .iter()
+            .filter(|node| node.kind == ModuleKind::Library)
+            .map(|node| {
+                (
+                    format!("{}Provider::class", node.name.to_case(Case::Pascal)),
+                    node,
+                )
+            })
+            .collect();
+
+        let mut edges = BTreeSet::new();
+        for node in &nodes {
+            let module = Path::new(node.kind.directory()).join(&node.name);
+            let mut add = |to: &Node, target: Target, via: Via| {
+                if to.name != node.name {
+                    edges.insert(Edge {
+                        from: node.name.clone(),
+                        to: to.name.clone(),
+                        target,
+                        via,
+                    });
+                }
+            };
+
+            let (_, build) = Dsl::detect(fs, &module.join("impl"), "build.gradle");
+            let source = fs.read_to_string(&build).unwrap_or_default();
+            for (receiver, method) in kotlin::block_calls(&source, "dependencies") {
+                let target = match method.as_str() {
+                    "api" => Target::Api,
+                    "impl" => Target::Impl,
+                    "all" => Target::All,
+                    _ => continue,
+                };
+                if let Some(to) = accessors.get(&receiver) {
+                    add(to, target, Via::Build);
+                }
+            }
+            for project in kotlin::block_strings(&source, "dependencies") {
+                let mut parts = project.trim_start_matches(':').split(':');
+                let (Some(directory), Some(name), Some(target), None) =
+                    (parts.next(), parts.next(), parts.next(), parts.next())
+                else {
+                    continue;
+                };
+                let target = match target {
+                    "api" => Target::Api,
+                    "impl" => Target::Impl,
+                    _ => continue,
+                };
+                if let Some(to) = nodes
+                    .iter()
+                    .find(|to| to.kind.directory() == directory && to.name == name)
+                {
+                    add(to, target, Via::Build);
+                }
+            }
+
+            let mut sources = Vec::new();
+            kotlin_files(fs, &module.join("impl/src"), &mut sources);
+            for path in sources {
+                let source = fs.read_to_string(&path).unwrap_or_default();
+                for entry in kotlin::array_entries(&source, "FeatureRoot", "dependencies") {
+                    if let Some(to) = providers.get(&entry) {
+                        add(to, Target::Api, Via::Provider);
+                    }
+                }
+            }
+        }
+
+        let edges: Vec<Edge> = edges.into_iter().collect();
+        let cycles = cycles(&nodes, &edges);
+        Self {
+            nodes,
+            edges,
+            cycles,
+        }
+    }
+
+    /// Dependencies on impl modules, which break the api/impl split
+    pub fn impl_dependencies(&self) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(|edge| edge.breaks_api_split())
+    }
+
+    fn in_cycle(&self, edge: &Edge) -> bool {
+        self.cycles
+            .iter()
+            .any(|cycle| cycle.contains(&edge.from) && cycle.contains(&edge.to))
+    }
+
+    /// One `(from, to)` pair per dependent modules, with the non-api targets and whether it's
+    /// flagged
+    fn links(&self) -> Vec<(&str, &str, Vec<Target>, bool)> {
+        let mut links: Vec<(&str, &str, Vec<Target>, bool)> = Vec::new();
+        for edge in &self.edges {
+            let flagged = edge.breaks_api_split() || self.in_cycle(edge);
+            match links.last_mut() {
+                Some((from, to, targets, link_flagged)) if *from == edge.from && *to == edge.to => {
+                    if edge.target != Target::Api && !targets.contains(&edge.target) {
+                        targets.push(edge.target);
+                    }
+                    *link_flagged |= flagged;
+                }
+                _ => links.push((
+                    &edge.from,
+                    &edge.to,
+                    Some(edge.target)
+                        .filter(|target| *target != Target::Api)
+                        .into_iter()
+                        .collect(),
+                    flagged,
+                )),
+            }
+        }
+        links
+    }
+
+    /// Descriptions of the cycles and impl dependencies
+    pub fn issues(&self) -> Vec<String> {
+        // The members are sorted, not in the order of their edges
+        let cycles = self
+            .cycles
+            .iter()
+            .map(|cycle| format!("cycle between {}", cycle.join(", ")));
+        let impl_dependencies = self.impl_dependencies().map(|edge| {
+            format!(
+                "impl dependency: {} -> {} ({})",
+                edge.from,
+                edge.to,
+                target_name(edge.target)
+            )
+        });
+        cycles.chain(impl_dependencies).collect()
+    }
+
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Mermaid => self.to_mermaid(),
+            GraphFormat::Json => {
+                serde_json::to_string_pretty(self).expect("Graphs should always be serializable")
+            }
+        }
+    }
+
+    fn to_dot(&self) -> String {
+        let mut lines = vec!["digraph modules {".to_string()];
+        lines.extend(
+            self.issues()
+                .iter()
+                .map(|issue| format!("    // {}", issue)),
+        );
+        for node in &self.nodes {
+            let shape = match node.kind {
+                ModuleKind::Feature => "box",
+                ModuleKind::Library => "ellipse",
+            };
+            lines.push(format!("    \"{}\" [shape={}];", node.name, shape));
+        }
+        for (from, to, targets, flagged) in self.links() {
+            let mut attributes = Vec::new();
+            if !targets.is_empty() {
+                attributes.push(format!("label=\"{}\"", target_names(&targets)));
+            }
+            if flagged {
+                attributes.push("color=red".to_string());
+            }
+            let attributes = if attributes.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", attributes.join(", "))
+            };
+            lines.push(format!("    \"{}\" -> \"{}\"{};", from, to, attributes));
+        }
+        lines.push("}".to_string());
+        lines.join("\n") + "\n"
+    }
+
+    fn to_mermaid(&self) -> String {
+        let mut lines = vec!["graph LR".to_string()];
+        lines.extend(
+            self.issues()
+                .iter()
+                .map(|issue| format!("    %% {}", issue)),
+        );
+        let ids: BTreeMap<&str, String> = self
+            .nodes
+            .iter()
+            .map(|node| (node.name.as_str(), node.name.to_case(Case::Snake)))
+            .collect();
+        for node in &self.nodes {
+            let (open, close) = match node.kind {
+                ModuleKind::Feature => ("[", "]"),
+                ModuleKind::Library => ("([", "])"),
+            };
+            lines.push(format!(
+                "    {}{}\"{}\"{}",
+                ids[node.name.as_str()],
+                open,
+                node.name,
+                close
+            ));
+        }
+        let mut flagged_links = Vec::new();
+        for (index, (from, to, targets, flagged)) in self.links().into_iter().enumerate() {
+            let arrow = if targets.is_empty() {
+                "-->".to_string()
+            } else {
+                format!("-->|{}|", target_names(&targets))
+            };
+            lines.push(format!("    {} {} {}", ids[from], arrow, ids[to]));
+            if flagged {
+                flagged_links.push(index.to_string());
+            }
+        }
+        if !flagged_links.is_empty() {
+            lines.push(format!(
+                "    linkStyle {} stroke:red",
+                flagged_links.join(",")
+            ));
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+fn target_name(target: Target) -> &'static str {
+    match target {
+        Target::Api => "api",
+        Target::Impl => "impl",
+        Target::All => "all",
+    }
+}
+
+fn target_names(targets: &[Target]) -> String {
+    targets
+        .iter()
+        .map(|target| target_name(*target))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Strongly connected groups of more than one module, found with Tarjan's algorithm
+fn cycles(nodes: &[Node], edges: &[Edge]) -> Vec<Vec<String>> {
+    struct State<'a> {
+        edges: &'a [Edge],
+        index: BTreeMap<&'a str, usize>,
+        low: BTreeMap<&'a str, usize>,
+        stack: Vec<&'a str>,
+        cycles: Vec<Vec<String>>,
+    }
+
+    fn visit<'a>(state: &mut State<'a>, node: &'a str) {
+        let index = state.index.len();
+        state.index.insert(node, index);
+        state.low.insert(node, index);
+        state.stack.push(node);
+
+        let edges = state.edges;
+        for edge in edges.iter().filter(|edge| edge.from == node) {
+            let next = edge.to.as_str();
+            if !state.index.contains_key(next) {
+                visit(state, next);
+                let low = state.low[node].min(state.low[next]);
+                state.low.insert(node, low);
+            } else if state.stack.contains(&next) {
+                let low = state.low[node].min(state.index[next]);
+                state.low.insert(node, low);
+            }
+        }
+
+        if state.low[node] == state.index[node] {
+            let position = state
+                .stack
+                .iter()
+                .rposition(|member| *member == node)
+                .expect("The node is on the stack");
+            let mut component: Vec<String> = state
+                .stack
+                .split_off(position)
+                .into_iter()
+                .map(str::to_string)
+                .collect();
+            if component.len() > 1 {
+                component.sort();
+                state.cycles.push(component);
+            }
+        }
+    }
+
+    let mut state = State {
+        edges,
+        index: BTreeMap::new(),
+        low: BTreeMap::new(),
+        stack: Vec::new(),
+        cycles: Vec::new(),
+    };
+    for node in nodes {
+        if !state.index.contains_key(node.name.as_str()) {
+            visit(&mut state, &node.name);
+        }
+    }
+    state.cycles.sort();
+    state.cycles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::file_system::RootedFileSystem;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn sample_project(name: &str) -> std::path::PathBuf {
+        let root = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&root);
+        write(
+            &root,
+            "feature/home/impl/build.gradle.kts",
+            "dependencies {\n    implementation(home.api())\n    implementation(detail.api())\n    implementation(savedFaces.api())\n}\n",
+        );
+        write(
+            &root,
+            "feature/home/impl/src/main/kotlin/a/root/HomeFeatureRoot.kt",
+            "@FeatureRoot(\n    dependencies = [\n        PlatformProvider::class,\n        SavedFacesProvider::class,\n    ],\n)\ninterface HomeFeatureRoot\n",
+        );
+        write(
+            &root,
+            "feature/detail/impl/build.gradle",
+            "dependencies {\n    implementation project(':feature:home:impl')\n}\n",
+        );
+        write(
+            &root,
+            "library/saved-faces/impl/build.gradle.kts",
+            "dependencies {\n    implementation(savedFaces.api())\n}\n",
+        );
+        root
+    }
+
+    #[test]
+    fn load_graph() {
+        let root = sample_project("feature_generator_graph_load");
+
+        let graph = ModuleGraph::load(&RootedFileSystem::new(&root));
+
+        let edge = |from: &str, to: &str, target, via| Edge {
+            from: from.into(),
+            to: to.into(),
+            target,
+            via,
+        };
+        assert_eq!(
+            vec![
+                Node {
+                    name: "detail".into(),
+                    kind: ModuleKind::Feature
+                },
+                Node {
+                    name: "home".into(),
+                    kind: ModuleKind::Feature
+                },
+                Node {
+                    name: "saved-faces".into(),
+                    kind: ModuleKind::Library
+                },
+            ],
+            graph.nodes
+        );
+        assert_eq!(
+            vec![
+                edge("detail", "home", Target::Impl, Via::Build),
+                edge("home", "detail", Target::Api, Via::Build),
+                edge("home", "saved-faces", Target::Api, Via::Build),
+                edge("home", "saved-faces", Target::Api, Via::Provider),
+            ],
+            graph.edges
+        );
+        assert_eq!(
+            vec![vec!["detail".to_string(), "home".to_string()]],
+            graph.cycles
+        );
+        assert_eq!(
+            vec![
+                "cycle between detail, home".to_string(),
+                "impl dependency: detail -> home (impl)".to_string(),
+            ],
+            graph.issues()
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn render_graph() {
+        let root = sample_project("feature_generator_graph_render");
+        let graph = ModuleGraph::load(&RootedFileSystem::new(&root));
+
+        assert_eq!(
+            "digraph modules {
+    // cycle between detail, home
+    // impl dependency: detail -> home (impl)
+    \"detail\" [shape=box];
+    \"home\" [shape=box];
+    \"saved-faces\" [shape=ellipse];
+    \"detail\" -> \"home\" [label=\"impl\", color=red];
+    \"home\" -> \"detail\" [color=red];
+    \"home\" -> \"saved-faces\";
+}
+",
+            graph.render(GraphFormat::Dot)
+        );
+        assert_eq!(
+            "graph LR
+    %% cycle between detail, home
+    %% impl dependency: detail -> home (impl)
+    detail[\"detail\"]
+    home[\"home\"]
+    saved_faces([\"saved-faces\"])
+    detail -->|impl| home
+    home --> detail
+    home --> saved_faces
+    linkStyle 0,1 stroke:red
+",
+            graph.render(GraphFormat::Mermaid)
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,179 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::file_system::{FileSystem, RealFileSystem};
+use super::gen_context::GenCtx;
+use super::render::Insertion;
+
+/// Every generation run so far, oldest first
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+#[serde(transparent)]
+pub struct History {
+    pub entries: Vec<HistoryEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub command: String,
+    pub context: GenCtx,
+    /// Seconds since the unix epoch
+    pub timestamp: u64,
+    pub created: Vec<CreatedFile>,
+    pub insertions: Vec<Insertion>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct CreatedFile {
+    pub path: PathBuf,
+    /// SHA-256 of the content as it was generated
+    pub hash: String,
+}
+
+impl HistoryEntry {
+    pub fn new(context: GenCtx, created: Vec<CreatedFile>, insertions: Vec<Insertion>) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            command: context.command_line(),
+            context,
+            timestamp,
+            created,
+            insertions,
+        }
+    }
+}
+
+impl History {
+    /// Reads the history at `path`, an empty one if nothing was generated yet
+    pub fn load(path: &Path) -> Self {
+        Self::load_from(&RealFileSystem, path)
+    }
+
+    /// Like `load`, reading through `fs`
+    pub fn load_from(fs: &dyn FileSystem, path: &Path) -> Self {
+        let Ok(content) = fs.read_to_string(path) else {
+            return Self::default();
+        };
+
+        serde_json::from_str(&content).unwrap_or_else(|error| {
+            panic!(
+                "Couldn't parse generation history!\npath: {:?}\n\nDetails\n{}",
+                path, error
+            )
+        })
+    }
+
+    pub fn save(&self, path: &Path) {
+        self.save_to(&RealFileSystem, path)
+    }
+
+    /// Like `save`, writing through `fs`
+    pub fn save_to(&self, fs: &dyn FileSystem, path: &Path) {
+        let content =
+            serde_json::to_string_pretty(self).expect("History should always be serializable");
+        fs.write(path, &(content + "\n")).unwrap_or_else(|_| {
+            panic!(
+                "Error during writing generation history!\nFile path: {}",
+                path.display()
+            )
+        });
+    }
+
+    /// Appends `entry` to the history stored at `path`
+    pub fn append(fs: &dyn FileSystem, path: &Path, entry: HistoryEntry) {
+        let mut history = Self::load_from(fs, path);
+        history.entries.push(entry);
+        history.save_to(fs, path);
+    }
+
+    /// Whether `path` was created by a generation, and is unchanged since
+    pub fn is_generated(&self, path: &Path, content: &str) -> bool {
+        let hash = content_hash(content);
+        self.entries
+            .iter()
+            .flat_map(|entry| &entry.created)
+            .any(|file| file.path == path && file.hash == hash)
+    }
+}
+
+/// SHA-256 of `content` as lowercase hex, also the base of template versions
+pub fn content_hash(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::gen_context::{AppCtx, Library};
+
+    fn entry() -> HistoryEntry {
+        let context = Library {
+            app_context: AppCtx {
+                is_testing: false,
+                app_name: "my-app".into(),
+                base_package: "com.my.app".into(),
+            },
+            library_name: "saved-faces".into(),
+            apps: Vec::new(),
+        };
+
+        HistoryEntry::new(
+            context.into(),
+            vec![CreatedFile {
+                path: "library/saved-faces/api/build.gradle.kts".into(),
+                hash: content_hash("plugins {}\n"),
+            }],
+            vec![Insertion {
+                path: "settings.gradle.kts".into(),
+                line: 30,
+                lines: vec!["include(\":library:saved-faces:api\")".into()],
+                replaced: Vec::new(),
+            }],
+        )
+    }
+
+    #[test]
+    fn append_and_load() {
+        let path = std::env::temp_dir().join("feature_generator_history/history.json");
+        let _ = std::fs::remove_file(&path);
+
+        History::append(&RealFileSystem, &path, entry());
+        History::append(&RealFileSystem, &path, entry());
+
+        let history = History::load(&path);
+        assert_eq!(2, history.entries.len());
+        assert_eq!("gen-lib saved-faces", history.entries[0].command);
+        assert_eq!(entry().insertions, history.entries[1].insertions);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn is_generated() {
+        let history = History {
+            entries: vec![entry()],
+        };
+        let path = Path::new("library/saved-faces/api/build.gradle.kts");
+
+        assert!(history.is_generated(path, "plugins {}\n"));
+        assert!(!history.is_generated(path, "plugins { edited }\n"));
+    }
+
+    #[test]
+    fn content_hash_is_sha256() {
+        assert_eq!(
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            content_hash("")
+        );
+    }
+}
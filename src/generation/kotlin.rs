@@ -0,0 +1,1164 @@
+use std::ops::Range;
+
+/// Replaces the `removed` lines starting at the 0-based `index` with the `inserted` ones
+#[derive(Debug, PartialEq)]
+pub struct Edit {
+    pub index: usize,
+    pub removed: Vec<String>,
+    pub inserted: Vec<String>,
+}
+
+impl Edit {
+    fn insert(index: usize, inserted: Vec<String>) -> Self {
+        Self {
+            index,
+            removed: Vec::new(),
+            inserted,
+        }
+    }
+}
+
+/// Adds `entry` to the `argument = [...]` array of the `@annotation(...)`, e.g.
+/// `features = [...]` of `@Scaffold`, keeping the entries alphabetically ordered.
+/// `None` if there's no such argument.
+pub fn add_array_entry(
+    source: &str,
+    annotation: &str,
+    argument: &str,
+    entry: &str,
+) -> Option<Edit> {
+    let tokens = tokenize(source);
+    let (open, close) = annotation_array(&tokens, annotation, argument)?;
+    let elements = elements(&tokens, open, close);
+    let lines: Vec<&str> = source.lines().collect();
+    let close_line = line_of(source, tokens[close].start);
+
+    let next = elements
+        .iter()
+        .find(|element| sort_key(element_text(source, &tokens, element)) > sort_key(entry));
+
+    if let Some(next) = next {
+        let token = &tokens[next.start];
+        return Some(if starts_line(source, token) {
+            let line = line_of(source, token.start);
+            Edit::insert(
+                line,
+                vec![format!("{}{},", indentation(lines[line]), entry)],
+            )
+        } else {
+            insert_in_line(source, token.start, &format!("{}, ", entry))
+        });
+    }
+
+    let Some(last) = elements.last() else {
+        return Some(if line_of(source, tokens[open].start) == close_line {
+            insert_in_line(source, tokens[open].end(), entry)
+        } else {
+            Edit::insert(
+                close_line,
+                vec![format!("{}    {},", indentation(lines[close_line]), entry)],
+            )
+        });
+    };
+
+    let last_token = &tokens[last.end - 1];
+    let last_line = line_of(source, last_token.start);
+    let indent = indentation(lines[line_of(source, tokens[last.start].start)]);
+    Some(if last_line == close_line {
+        insert_in_line(source, last_token.end(), &format!(", {}", entry))
+    } else if tokens[close - 1].is(",") {
+        Edit::insert(
+            line_of(source, tokens[close - 1].start) + 1,
+            vec![format!("{}{},", indent, entry)],
+        )
+    } else {
+        // Entries don't have trailing commas, so the last one gets one instead of the new one
+        let mut edit = insert_in_line(source, last_token.end(), ",");
+        edit.inserted.push(format!("{}{}", indent, entry));
+        edit
+    })
+}
+
+/// Elements of the `argument = [...]` array of the `@annotation(...)`, as written
+pub fn array_entries(source: &str, annotation: &str, argument: &str) -> Vec<String> {
+    let tokens = tokenize(source);
+    annotation_array(&tokens, annotation, argument).map_or_else(Vec::new, |(open, close)| {
+        elements(&tokens, open, close)
+            .iter()
+            .map(|element| element_text(source, &tokens, element).to_string())
+            .collect()
+    })
+}
+
+/// Receivers and names of the `receiver.method()` calls anywhere in the top level
+/// `block { ... }`, e.g. `("home", "api")` for `implementation(home.api())`
+pub fn block_calls(source: &str, block: &str) -> Vec<(String, String)> {
+    let tokens = tokenize(source);
+    let Some((open, close)) = top_level_block(&tokens, block) else {
+        return Vec::new();
+    };
+    tokens[open + 1..close]
+        .windows(4)
+        .filter(|window| {
+            window[0].kind == TokenKind::Identifier
+                && window[1].is(".")
+                && window[2].kind == TokenKind::Identifier
+                && window[3].is("(")
+        })
+        .map(|window| (window[0].text.to_string(), window[2].text.to_string()))
+        .collect()
+}
+
+/// Contents of the string literals anywhere in the top level `block { ... }`, without quotes
+pub fn block_strings(source: &str, block: &str) -> Vec<String> {
+    let tokens = tokenize(source);
+    let Some((open, close)) = top_level_block(&tokens, block) else {
+        return Vec::new();
+    };
+    tokens[open + 1..close]
+        .iter()
+        .filter(|token| token.kind == TokenKind::Literal)
+        .filter_map(|token| {
+            let quote = token
+                .text
+                .chars()
+                .next()
+                .filter(|c| *c == '"' || *c == '\'')?;
+            let text = token.text.trim_matches(quote);
+            Some(text.to_string())
+        })
+        .collect()
+}
+
+/// Value of `argument = ...` in the `@annotation(...)`, as written
+pub fn annotation_argument(source: &str, annotation: &str, argument: &str) -> Option<String> {
+    let tokens = tokenize(source);
+    let (open, close) = annotation_parens(&tokens, annotation)?;
+    elements(&tokens, open, close)
+        .into_iter()
+        .find(|element| {
+            element.len() > 2
+                && tokens[element.start].is_identifier(argument)
+                && tokens[element.start + 1].is("=")
+        })
+        .map(|element| element_text(source, &tokens, &(element.start + 2..element.end)).to_string())
+}
+
+/// Name of the class, object or interface annotated with `@annotation`
+pub fn annotated_declaration(source: &str, annotation: &str) -> Option<String> {
+    let tokens = tokenize(source);
+    let start = tokens
+        .windows(2)
+        .position(|window| window[0].is("@") && window[1].is_identifier(annotation))?;
+    let mut index = start + 2;
+    if tokens.get(index).is_some_and(|token| token.is("(")) {
+        index = matching(&tokens, index)? + 1;
+    }
+    let keyword = tokens[index..].iter().position(|token| {
+        ["class", "object", "interface"]
+            .iter()
+            .any(|keyword| token.is_identifier(keyword))
+    })?;
+    tokens
+        .get(index + keyword + 1)
+        .filter(|token| token.kind == TokenKind::Identifier)
+        .map(|token| token.text.to_string())
+}
+
+/// Arguments of the first `function(...)` call, as written
+pub fn call_arguments(source: &str, function: &str) -> Option<Vec<String>> {
+    let tokens = tokenize(source);
+    let open = tokens
+        .windows(2)
+        .position(|window| window[0].is_identifier(function) && window[1].is("("))?
+        + 1;
+    let close = matching(&tokens, open)?;
+    Some(
+        elements(&tokens, open, close)
+            .iter()
+            .map(|element| element_text(source, &tokens, element).to_string())
+            .collect(),
+    )
+}
+
+/// Type arguments of the first use of the generic `type_name<...>`, as written
+pub fn type_arguments(source: &str, type_name: &str) -> Option<String> {
+    let tokens = tokenize(source);
+    let open = tokens
+        .windows(2)
+        .position(|window| window[0].is_identifier(type_name) && window[1].is("<"))?
+        + 1;
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().skip(open) {
+        if token.is("<") {
+            depth += 1;
+        } else if token.is(">") {
+            depth -= 1;
+            if depth == 0 {
+                return (index > open + 1)
+                    .then(|| element_text(source, &tokens, &(open + 1..index)).to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Whether `entry` is an element of the `argument = [...]` array of the `@annotation(...)`
+pub fn has_array_entry(source: &str, annotation: &str, argument: &str, entry: &str) -> bool {
+    let tokens = tokenize(source);
+    annotation_array(&tokens, annotation, argument).is_some_and(|(open, close)| {
+        elements(&tokens, open, close)
+            .iter()
+            .any(|element| element_text(source, &tokens, element) == entry)
+    })
+}
+
+/// Removes `entry` and its comma from the `argument = [...]` array of the `@annotation(...)`,
+/// along with its line if it had one for itself. `None` if it isn't there.
+pub fn remove_array_entry(
+    source: &str,
+    annotation: &str,
+    argument: &str,
+    entry: &str,
+) -> Option<Edit> {
+    let tokens = tokenize(source);
+    let (open, close) = annotation_array(&tokens, annotation, argument)?;
+    let elements = elements(&tokens, open, close);
+    let position = elements
+        .iter()
+        .position(|element| element_text(source, &tokens, element) == entry)?;
+    let element = &elements[position];
+    let lines: Vec<&str> = source.lines().collect();
+
+    let first = &tokens[element.start];
+    let comma = tokens[element.end].is(",").then_some(element.end);
+    let last = &tokens[comma.unwrap_or(element.end - 1)];
+    let next = &tokens[comma.map_or(element.end, |comma| comma + 1)];
+    let first_line = line_of(source, first.start);
+    let last_line = line_of(source, last.start);
+
+    if starts_line(source, first) && line_of(source, next.start) > last_line {
+        return Some(Edit {
+            index: first_line,
+            removed: lines[first_line..=last_line]
+                .iter()
+                .map(|line| line.to_string())
+                .collect(),
+            inserted: Vec::new(),
+        });
+    }
+
+    // The entry shares its line with others, so only its text is cut out
+    let (start, end) = match comma {
+        Some(_) => (first.start, next.start),
+        None if position > 0 => (tokens[element.start - 2].end(), last.end()),
+        None => (first.start, last.end()),
+    };
+    let line = line_of(source, start);
+    if line != line_of(source, end) {
+        return None;
+    }
+    let line_offset = line_start(source, start);
+    let text = lines[line];
+    Some(Edit {
+        index: line,
+        removed: vec![text.to_string()],
+        inserted: vec![format!(
+            "{}{}",
+            &text[..start - line_offset],
+            &text[end - line_offset..]
+        )],
+    })
+}
+
+/// Adds `entries` as the first statements of the top level `block { ... }`, e.g. `dependencies`.
+/// Blocks nested in others, like the one in `buildscript`, are left alone. Works the same for
+/// groovy scripts.
+pub fn add_block_entries(source: &str, block: &str, entries: &[String]) -> Option<Edit> {
+    let tokens = tokenize(source);
+    let (open, close) = top_level_block(&tokens, block)?;
+    let lines: Vec<&str> = source.lines().collect();
+
+    let open_line = line_of(source, tokens[open].start);
+    let next_line = line_of(source, tokens[open + 1].start);
+    let line = lines[open_line];
+    let inner_indent = format!("{}    ", indentation(line));
+
+    if next_line != open_line {
+        let indent = if open + 1 == close {
+            inner_indent
+        } else {
+            indentation(lines[next_line]).to_string()
+        };
+        return Some(Edit::insert(
+            open_line + 1,
+            entries
+                .iter()
+                .map(|entry| format!("{}{}", indent, entry))
+                .collect(),
+        ));
+    }
+
+    // The block continues on the line of its brace, so the line is split up
+    let column = tokens[open].end() - line_start(source, tokens[open].start);
+    let rest = line[column..].trim_start();
+    let rest_indent = if rest.starts_with('}') {
+        indentation(line)
+    } else {
+        &inner_indent
+    };
+    let mut inserted = vec![line[..column].to_string()];
+    inserted.extend(
+        entries
+            .iter()
+            .map(|entry| format!("{}{}", inner_indent, entry)),
+    );
+    inserted.push(format!("{}{}", rest_indent, rest));
+    Some(Edit {
+        index: open_line,
+        removed: vec![line.to_string()],
+        inserted,
+    })
+}
+
+/// Whether the top level `block { ... }` has an `entry` statement on its own line
+pub fn has_block_entry(source: &str, block: &str, entry: &str) -> bool {
+    block_entry_line(source, block, entry).is_some()
+}
+
+/// Removes the line of the `entry` statement from the top level `block { ... }`. `None` if it
+/// isn't there.
+pub fn remove_block_entry(source: &str, block: &str, entry: &str) -> Option<Edit> {
+    let line = block_entry_line(source, block, entry)?;
+    Some(Edit {
+        index: line,
+        removed: vec![source.lines().nth(line)?.to_string()],
+        inserted: Vec::new(),
+    })
+}
+
+/// Adds `import path` to the imports in alphabetical order, or below the package declaration if
+/// there are none
+pub fn add_import(source: &str, path: &str) -> Edit {
+    let import = format!("import {}", path);
+    if let Some(edit) =
+        add_grouped_lines(source, "import ", std::slice::from_ref(&import), |line| {
+            line
+        })
+    {
+        return edit;
+    }
+
+    let tokens = tokenize(source);
+    let package = top_level(&tokens)
+        .find(|token| token.is_identifier("package") && starts_line(source, token))
+        .map(|token| line_of(source, token.start));
+    match package {
+        Some(line) => Edit::insert(line + 1, vec![String::new(), import]),
+        None => Edit::insert(0, vec![import, String::new()]),
+    }
+}
+
+/// Whether the file has an `import path` line
+pub fn has_import(source: &str, path: &str) -> bool {
+    import_line(source, &tokenize(source), path).is_some()
+}
+
+/// Removes `import path`, unless the imported name is still used elsewhere in the file. `None`
+/// if the import is missing or still needed.
+pub fn remove_import(source: &str, path: &str) -> Option<Edit> {
+    let tokens = tokenize(source);
+    let name = path.rsplit('.').next().unwrap_or(path);
+    let uses = tokens
+        .iter()
+        .filter(|token| token.is_identifier(name))
+        .count();
+    if uses > 1 {
+        return None;
+    }
+
+    let line = import_line(source, &tokens, path)?;
+    Some(Edit {
+        index: line,
+        removed: vec![source.lines().nth(line)?.to_string()],
+        inserted: Vec::new(),
+    })
+}
+
+/// Index of the top level `import path` line
+fn import_line(source: &str, tokens: &[Token], path: &str) -> Option<usize> {
+    let import = format!("import {}", path);
+    let lines: Vec<&str> = source.lines().collect();
+    top_level(tokens)
+        .filter(|token| token.is_identifier("import") && starts_line(source, token))
+        .map(|token| line_of(source, token.start))
+        .find(|line| lines[*line].trim() == import)
+}
+
+/// Adds `entries` to the group of top level lines starting with `prefix` they fit in best, in
+/// alphabetical order. Groups are separated by blank lines or comments, like
+/// `// ===== feature modules =====`, and the best fit is the group whose `group_key`s share the
+/// longest prefix with the one of the first entry. `None` if there's no line with `prefix`.
+pub fn add_grouped_lines(
+    source: &str,
+    prefix: &str,
+    entries: &[String],
+    group_key: impl Fn(&str) -> &str,
+) -> Option<Edit> {
+    let tokens = tokenize(source);
+    let lines: Vec<&str> = source.lines().collect();
+    let mut candidates: Vec<usize> = top_level(&tokens)
+        .filter(|token| starts_line(source, token))
+        .map(|token| line_of(source, token.start))
+        .filter(|line| lines[*line].trim_start().starts_with(prefix))
+        .collect();
+    candidates.dedup();
+
+    let mut groups: Vec<Range<usize>> = Vec::new();
+    for line in candidates {
+        match groups.last_mut() {
+            Some(group) if group.end == line => group.end += 1,
+            _ => groups.push(line..line + 1),
+        }
+    }
+
+    let entry = entries.first()?;
+    let key = group_key(entry);
+    // Ties go to the group whose range the entry falls in, or to the later one
+    let score = |group: &Range<usize>| {
+        let group_lines = &lines[group.clone()];
+        let prefix = group_lines
+            .iter()
+            .map(|line| common_prefix(group_key(line.trim_start()), key))
+            .max();
+        let first = sort_key(group_lines[0].trim_start());
+        let last = sort_key(group_lines[group_lines.len() - 1].trim_start());
+        let within = first <= sort_key(entry) && sort_key(entry) <= last;
+        (prefix, within)
+    };
+    let group = groups
+        .iter()
+        .fold(None, |best: Option<&Range<usize>>, group| match best {
+            Some(best) if score(best) > score(group) => Some(best),
+            _ => Some(group),
+        })?;
+
+    let index = group
+        .clone()
+        .find(|line| sort_key(lines[*line].trim_start()) > sort_key(entry))
+        .unwrap_or(group.end);
+    let indent = indentation(lines[group.start]);
+    Some(Edit::insert(
+        index,
+        entries
+            .iter()
+            .map(|entry| format!("{}{}", indent, entry))
+            .collect(),
+    ))
+}
+
+/// Whether a top level declaration of the file is annotated with `@annotation`, e.g. the
+/// application class with `@Scaffold`. Comments and strings don't count.
+pub fn has_annotation(source: &str, annotation: &str) -> bool {
+    let tokens = tokenize(source);
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate() {
+        if depth == 0
+            && token.is("@")
+            && tokens
+                .get(index + 1)
+                .is_some_and(|next| next.is_identifier(annotation))
+        {
+            return true;
+        }
+        depth += token.nesting();
+    }
+    false
+}
+
+/// Adds `entries` at the end of the file
+pub fn append_lines(source: &str, entries: &[String]) -> Edit {
+    Edit::insert(source.lines().count(), entries.to_vec())
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum TokenKind {
+    Identifier,
+    Punctuation,
+    Literal,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    kind: TokenKind,
+    text: &'a str,
+    /// Byte offset in the source
+    start: usize,
+}
+
+impl Token<'_> {
+    fn is(&self, punctuation: &str) -> bool {
+        self.kind == TokenKind::Punctuation && self.text == punctuation
+    }
+
+    fn is_identifier(&self, name: &str) -> bool {
+        self.kind == TokenKind::Identifier && self.text == name
+    }
+
+    fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    /// +1 for opening brackets, -1 for closing ones
+    fn nesting(&self) -> isize {
+        match self.kind {
+            TokenKind::Punctuation => match self.text {
+                "{" | "(" | "[" => 1,
+                "}" | ")" | "]" => -1,
+                _ => 0,
+            },
+            _ => 0,
+        }
+    }
+}
+
+/// Splits Kotlin source into tokens, skipping whitespace and comments. Strings and numbers are
+/// kept as single literals, so nothing inside them is mistaken for code.
+fn tokenize(source: &str) -> Vec<Token<'_>> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let start = index;
+        let kind = match bytes[index] {
+            byte if byte.is_ascii_whitespace() => {
+                index += 1;
+                continue;
+            }
+            b'/' if bytes.get(index + 1) == Some(&b'/') => {
+                index = find(bytes, index, b"\n");
+                continue;
+            }
+            b'/' if bytes.get(index + 1) == Some(&b'*') => {
+                index = block_comment_end(bytes, index);
+                continue;
+            }
+            b'"' if bytes[index..].starts_with(b"\"\"\"") => {
+                index = find(bytes, index + 3, b"\"\"\"") + 3;
+                while bytes.get(index) == Some(&b'"') {
+                    index += 1;
+                }
+                TokenKind::Literal
+            }
+            b'"' => {
+                index = string_end(bytes, index);
+                TokenKind::Literal
+            }
+            b'\'' => {
+                index = char_end(bytes, index);
+                TokenKind::Literal
+            }
+            b'`' => {
+                index = find(bytes, index + 1, b"`") + 1;
+                TokenKind::Identifier
+            }
+            byte if byte.is_ascii_digit() => {
+                while index < bytes.len()
+                    && (is_identifier_part(bytes[index]) || bytes[index] == b'.')
+                {
+                    index += 1;
+                }
+                TokenKind::Literal
+            }
+            byte if is_identifier_part(byte) => {
+                while index < bytes.len() && is_identifier_part(bytes[index]) {
+                    index += 1;
+                }
+                TokenKind::Identifier
+            }
+            _ => {
+                index += 1;
+                TokenKind::Punctuation
+            }
+        };
+
+        let end = index.min(bytes.len());
+        tokens.push(Token {
+            kind,
+            text: &source[start..end],
+            start,
+        });
+        index = end;
+    }
+
+    tokens
+}
+
+fn is_identifier_part(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || !byte.is_ascii()
+}
+
+/// Index of the first `pattern` from `from`, or the end of `bytes`
+fn find(bytes: &[u8], from: usize, pattern: &[u8]) -> usize {
+    (from..bytes.len())
+        .find(|index| bytes[*index..].starts_with(pattern))
+        .unwrap_or(bytes.len())
+}
+
+fn block_comment_end(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 0;
+    let mut index = start;
+    while index < bytes.len() {
+        if bytes[index..].starts_with(b"/*") {
+            depth += 1;
+            index += 2;
+        } else if bytes[index..].starts_with(b"*/") {
+            depth -= 1;
+            index += 2;
+            if depth == 0 {
+                return index;
+            }
+        } else {
+            index += 1;
+        }
+    }
+    index
+}
+
+fn string_end(bytes: &[u8], start: usize) -> usize {
+    let mut index = start + 1;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'"' => return index + 1,
+            b'$' if bytes.get(index + 1) == Some(&b'{') => {
+                index = template_end(bytes, index + 1);
+            }
+            _ => index += 1,
+        }
+    }
+    index
+}
+
+/// End of the `${...}` template expression whose opening brace is at `start`
+fn template_end(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 0;
+    let mut index = start;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return index + 1;
+                }
+            }
+            b'"' => {
+                index = string_end(bytes, index);
+                continue;
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+    index
+}
+
+fn char_end(bytes: &[u8], start: usize) -> usize {
+    let mut index = start + 1;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'\'' => return index + 1,
+            _ => index += 1,
+        }
+    }
+    index
+}
+
+/// Tokens outside of any brackets
+fn top_level<'a, 'b>(tokens: &'b [Token<'a>]) -> impl Iterator<Item = &'b Token<'a>> {
+    let mut depth = 0;
+    tokens.iter().filter(move |token| {
+        let is_top_level = depth == 0 && token.nesting() == 0;
+        depth += token.nesting();
+        is_top_level
+    })
+}
+
+/// Index of the bracket closing the one at `open`
+fn matching(tokens: &[Token], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().skip(open) {
+        depth += token.nesting();
+        if depth == 0 {
+            return Some(index);
+        }
+    }
+    None
+}
+
+/// Indices of the braces of the top level `name { ... }` block
+fn top_level_block(tokens: &[Token], name: &str) -> Option<(usize, usize)> {
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate() {
+        if depth == 0
+            && token.is_identifier(name)
+            && tokens.get(index + 1).is_some_and(|next| next.is("{"))
+        {
+            return Some((index + 1, matching(tokens, index + 1)?));
+        }
+        depth += token.nesting();
+    }
+    None
+}
+
+/// Indices of the brackets of `argument = [...]` in `@annotation(...)`
+fn annotation_array(tokens: &[Token], annotation: &str, argument: &str) -> Option<(usize, usize)> {
+    let (open_paren, close_paren) = annotation_parens(tokens, annotation)?;
+
+    let mut depth = 0;
+    for index in open_paren + 1..close_paren {
+        if depth == 0
+            && tokens[index].is_identifier(argument)
+            && tokens[index + 1].is("=")
+            && tokens[index + 2].is("[")
+        {
+            return Some((index + 2, matching(tokens, index + 2)?));
+        }
+        depth += tokens[index].nesting();
+    }
+    None
+}
+
+/// Indices of the parentheses of the first `@annotation(...)`
+fn annotation_parens(tokens: &[Token], annotation: &str) -> Option<(usize, usize)> {
+    let start = tokens.windows(3).position(|window| {
+        window[0].is("@") && window[1].is_identifier(annotation) && window[2].is("(")
+    })?;
+    Some((start + 2, matching(tokens, start + 2)?))
+}
+
+/// Token ranges of the comma separated elements between the brackets at `open` and `close`
+fn elements(tokens: &[Token], open: usize, close: usize) -> Vec<Range<usize>> {
+    let mut elements = Vec::new();
+    let mut depth = 0;
+    let mut start = open + 1;
+    for (index, token) in tokens.iter().enumerate().take(close).skip(open + 1) {
+        if depth == 0 && token.is(",") {
+            elements.push(start..index);
+            start = index + 1;
+        }
+        depth += token.nesting();
+    }
+    elements.push(start..close);
+    elements.retain(|element| !element.is_empty());
+    elements
+}
+
+/// 0-based line of the `entry` statement directly in the top level `block { ... }`
+fn block_entry_line(source: &str, block: &str, entry: &str) -> Option<usize> {
+    let tokens = tokenize(source);
+    let (open, close) = top_level_block(&tokens, block)?;
+    let lines: Vec<&str> = source.lines().collect();
+
+    let mut depth = 0;
+    for token in &tokens[open + 1..close] {
+        let line = line_of(source, token.start);
+        if depth == 0 && starts_line(source, token) && lines[line].trim() == entry {
+            return Some(line);
+        }
+        depth += token.nesting();
+    }
+    None
+}
+
+/// Source text of the `element` tokens
+fn element_text<'a>(source: &'a str, tokens: &[Token], element: &Range<usize>) -> &'a str {
+    &source[tokens[element.start].start..tokens[element.end - 1].end()]
+}
+
+/// 0-based line of the byte `offset`
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count()
+}
+
+fn line_start(source: &str, offset: usize) -> usize {
+    source[..offset].rfind('\n').map_or(0, |index| index + 1)
+}
+
+fn starts_line(source: &str, token: &Token) -> bool {
+    source[line_start(source, token.start)..token.start]
+        .trim()
+        .is_empty()
+}
+
+/// Inserts `text` at the byte `offset`, replacing the line it's in
+fn insert_in_line(source: &str, offset: usize, text: &str) -> Edit {
+    let start = line_start(source, offset);
+    let line = source[start..].lines().next().unwrap_or_default();
+    let column = offset - start;
+    Edit {
+        index: line_of(source, offset),
+        removed: vec![line.to_string()],
+        inserted: vec![format!("{}{}{}", &line[..column], text, &line[column..])],
+    }
+}
+
+fn sort_key(entry: &str) -> String {
+    entry.to_lowercase()
+}
+
+fn common_prefix(a: &str, b: &str) -> usize {
+    a.chars().zip(b.chars()).take_while(|(a, b)| a == b).count()
+}
+
+fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(source: &str, edit: Edit) -> String {
+        let mut lines: Vec<String> = source.lines().map(str::to_string).collect();
+        lines.splice(edit.index..edit.index + edit.removed.len(), edit.inserted);
+        lines.join("\n") + "\n"
+    }
+
+    #[test]
+    fn tokenize_skips_comments_and_strings() {
+        let tokens = tokenize("// features = [\n/* a /* b */ */ val x = \"a ${ \"}\" } [\" // c\n");
+        let texts: Vec<&str> = tokens.iter().map(|token| token.text).collect();
+
+        assert_eq!(vec!["val", "x", "=", "\"a ${ \"}\" } [\""], texts);
+    }
+
+    #[test]
+    fn array_entry_in_multiline_array() {
+        let source = "@Scaffold(\n    libraries = [\n        Platform::class\n    ],\n    // features = [\n    features = [\n        StartFeatureRoot::class,\n    ],\n)\n";
+
+        let edit = add_array_entry(source, "Scaffold", "features", "HomeFeatureRoot::class");
+
+        assert_eq!(
+            "@Scaffold(\n    libraries = [\n        Platform::class\n    ],\n    // features = [\n    features = [\n        HomeFeatureRoot::class,\n        StartFeatureRoot::class,\n    ],\n)\n",
+            apply(source, edit.unwrap())
+        );
+    }
+
+    #[test]
+    fn array_entry_in_one_line_array() {
+        let source = "@Scaffold(features = [StartFeatureRoot::class], libraries = [])\n";
+
+        let features = add_array_entry(source, "Scaffold", "features", "HomeFeatureRoot::class");
+        let last = add_array_entry(source, "Scaffold", "features", "WelcomeFeatureRoot::class");
+        let libraries = add_array_entry(source, "Scaffold", "libraries", "Platform::class");
+
+        assert_eq!(
+            "@Scaffold(features = [HomeFeatureRoot::class, StartFeatureRoot::class], libraries = [])\n",
+            apply(source, features.unwrap())
+        );
+        assert_eq!(
+            "@Scaffold(features = [StartFeatureRoot::class, WelcomeFeatureRoot::class], libraries = [])\n",
+            apply(source, last.unwrap())
+        );
+        assert_eq!(
+            "@Scaffold(features = [StartFeatureRoot::class], libraries = [Platform::class])\n",
+            apply(source, libraries.unwrap())
+        );
+    }
+
+    #[test]
+    fn array_entry_at_the_end() {
+        let source = "@Scaffold(\n    libraries = [\n        Platform::class // core\n    ],\n    features = [\n        StartFeatureRoot::class,\n    ],\n)\n";
+
+        let libraries = add_array_entry(source, "Scaffold", "libraries", "SavedFaces::class");
+        let features = add_array_entry(source, "Scaffold", "features", "WelcomeFeatureRoot::class");
+
+        assert_eq!(
+            "@Scaffold(\n    libraries = [\n        Platform::class, // core\n        SavedFaces::class\n    ],\n    features = [\n        StartFeatureRoot::class,\n    ],\n)\n",
+            apply(source, libraries.unwrap())
+        );
+        assert_eq!(
+            "@Scaffold(\n    libraries = [\n        Platform::class // core\n    ],\n    features = [\n        StartFeatureRoot::class,\n        WelcomeFeatureRoot::class,\n    ],\n)\n",
+            apply(source, features.unwrap())
+        );
+    }
+
+    #[test]
+    fn array_entry_in_empty_multiline_array() {
+        let source = "@Scaffold(\n    features = [\n    ],\n)\n";
+
+        let edit = add_array_entry(source, "Scaffold", "features", "HomeFeatureRoot::class");
+
+        assert_eq!(
+            "@Scaffold(\n    features = [\n        HomeFeatureRoot::class,\n    ],\n)\n",
+            apply(source, edit.unwrap())
+        );
+    }
+
+    #[test]
+    fn array_entry_without_argument() {
+        assert_eq!(
+            None,
+            add_array_entry(
+                "@Scaffold(startFeature = A::class)\n",
+                "Scaffold",
+                "features",
+                "B"
+            )
+        );
+    }
+
+    #[test]
+    fn block_entry_in_top_level_block() {
+        let source = "buildscript {\n    dependencies {\n        classpath(a)\n    }\n}\n\n// dependencies {\ntestDependencies {\n}\n\ndependencies {\n    implementation(libs.android)\n}\n";
+
+        let edit = add_block_entries(
+            source,
+            "dependencies",
+            &["implementation(*home.all())".into()],
+        );
+
+        assert_eq!(
+            "buildscript {\n    dependencies {\n        classpath(a)\n    }\n}\n\n// dependencies {\ntestDependencies {\n}\n\ndependencies {\n    implementation(*home.all())\n    implementation(libs.android)\n}\n",
+            apply(source, edit.unwrap())
+        );
+    }
+
+    #[test]
+    fn block_entry_in_one_line_block() {
+        let edit = add_block_entries(
+            "dependencies {}\n",
+            "dependencies",
+            &["implementation(a)".into()],
+        );
+
+        assert_eq!(
+            "dependencies {\n    implementation(a)\n}\n",
+            apply("dependencies {}\n", edit.unwrap())
+        );
+    }
+
+    #[test]
+    fn import_in_alphabetical_order() {
+        let source = "package a\n\n// import z.Z\nimport b.B\nimport d.D\n\nimport java.io.File\n";
+
+        assert_eq!(
+            "package a\n\n// import z.Z\nimport b.B\nimport c.C\nimport d.D\n\nimport java.io.File\n",
+            apply(source, add_import(source, "c.C"))
+        );
+    }
+
+    #[test]
+    fn grouped_lines_go_to_best_fitting_group() {
+        let source = "// ===== feature modules =====\nval DependencyHandlerScope.camera get() = createProject(\":feature:camera\")\nval DependencyHandlerScope.landing get() = createProject(\":feature:landing\")\n\nval DependencyHandlerScope.appState get() = createProject(\":library:app-state\")\n";
+        fn group_key(line: &str) -> &str {
+            line.split_once("createProject(")
+                .map_or(line, |(_, path)| path)
+        }
+
+        let edit = add_grouped_lines(
+            source,
+            "val DependencyHandlerScope.",
+            &["val DependencyHandlerScope.home get() = createProject(\":feature:home\")".into()],
+            group_key,
+        );
+
+        assert_eq!(
+            "// ===== feature modules =====\nval DependencyHandlerScope.camera get() = createProject(\":feature:camera\")\nval DependencyHandlerScope.home get() = createProject(\":feature:home\")\nval DependencyHandlerScope.landing get() = createProject(\":feature:landing\")\n\nval DependencyHandlerScope.appState get() = createProject(\":library:app-state\")\n",
+            apply(source, edit.unwrap())
+        );
+    }
+
+    #[test]
+    fn grouped_lines_keep_entries_together() {
+        let source = "include(\":app\")\n\ninclude(\":feature:start:api\")\ninclude(\":feature:start:impl\")\ninclude(\":theme\")\n";
+
+        let edit = add_grouped_lines(
+            source,
+            "include(",
+            &[
+                "include(\":feature:home:api\")".into(),
+                "include(\":feature:home:impl\")".into(),
+            ],
+            |line| line,
+        );
+
+        assert_eq!(
+            "include(\":app\")\n\ninclude(\":feature:home:api\")\ninclude(\":feature:home:impl\")\ninclude(\":feature:start:api\")\ninclude(\":feature:start:impl\")\ninclude(\":theme\")\n",
+            apply(source, edit.unwrap())
+        );
+        assert_eq!(
+            None,
+            add_grouped_lines(
+                "rootProject.name = \"a\"\n",
+                "include(",
+                &["include(\":a\")".into()],
+                |line| line
+            )
+        );
+    }
+
+    #[test]
+    fn import_without_imports() {
+        assert_eq!(
+            "package a\n\nimport d.D\n\nclass A\n",
+            apply(
+                "package a\n\nclass A\n",
+                add_import("package a\n\nclass A\n", "d.D")
+            )
+        );
+    }
+
+    #[test]
+    fn annotation_outside_comments_and_strings() {
+        assert!(has_annotation(
+            "package a\n\n@Scaffold(features = [])\nclass FreshApp : Application()\n",
+            "Scaffold"
+        ));
+        assert!(!has_annotation(
+            "// @Scaffold\nval a = \"@Scaffold\"\n@ScaffoldLike\nclass A\n",
+            "Scaffold"
+        ));
+        assert!(!has_annotation(
+            "class A {\n    @Scaffold\n    fun b() {}\n}\n",
+            "Scaffold"
+        ));
+    }
+
+    #[test]
+    fn remove_array_entry_on_own_line() {
+        let source = "@FeatureRoot(\n    dependencies = [\n        PlatformProvider::class,\n        SavedFacesProvider::class,\n    ],\n)\n";
+
+        assert!(has_array_entry(
+            source,
+            "FeatureRoot",
+            "dependencies",
+            "SavedFacesProvider::class"
+        ));
+        let edit = remove_array_entry(
+            source,
+            "FeatureRoot",
+            "dependencies",
+            "SavedFacesProvider::class",
+        );
+
+        assert_eq!(
+            "@FeatureRoot(\n    dependencies = [\n        PlatformProvider::class,\n    ],\n)\n",
+            apply(source, edit.unwrap())
+        );
+        assert_eq!(
+            None,
+            remove_array_entry(source, "FeatureRoot", "dependencies", "Missing::class")
+        );
+    }
+
+    #[test]
+    fn remove_array_entry_in_line() {
+        let remove = |source: &str, entry: &str| {
+            apply(
+                source,
+                remove_array_entry(source, "Scaffold", "features", entry).unwrap(),
+            )
+        };
+
+        assert_eq!(
+            "@Scaffold(features = [B::class])\n",
+            remove("@Scaffold(features = [A::class, B::class])\n", "A::class")
+        );
+        assert_eq!(
+            "@Scaffold(features = [A::class])\n",
+            remove("@Scaffold(features = [A::class, B::class])\n", "B::class")
+        );
+        assert_eq!(
+            "@Scaffold(features = [])\n",
+            remove("@Scaffold(features = [A::class])\n", "A::class")
+        );
+    }
+
+    #[test]
+    fn remove_block_entry_only_at_top_level() {
+        let source = "buildscript {\n    dependencies {\n        implementation(home.api())\n    }\n}\n\ndependencies {\n    implementation(home.api())\n    implementation(libs.dagger)\n}\n";
+
+        let edit = remove_block_entry(source, "dependencies", "implementation(home.api())");
+
+        assert_eq!(
+            "buildscript {\n    dependencies {\n        implementation(home.api())\n    }\n}\n\ndependencies {\n    implementation(libs.dagger)\n}\n",
+            apply(source, edit.unwrap())
+        );
+    }
+
+    #[test]
+    fn remove_import_unless_used() {
+        let source = "package a\n\nimport b.Saved\nimport c.Other\n\nval x = Other\n";
+
+        assert_eq!(
+            "package a\n\nimport c.Other\n\nval x = Other\n",
+            apply(source, remove_import(source, "b.Saved").unwrap())
+        );
+        assert_eq!(None, remove_import(source, "c.Other"));
+        assert_eq!(None, remove_import(source, "d.Missing"));
+    }
+
+    #[test]
+    fn read_references() {
+        let build = "dependencies {\n    // implementation(old.api())\n    implementation(home.api(), libs.dagger)\n    implementation(*detail.all())\n    implementation project(':library:saved-faces:api')\n}\n";
+
+        assert_eq!(
+            vec![
+                ("home".to_string(), "api".to_string()),
+                ("detail".to_string(), "all".to_string())
+            ],
+            block_calls(build, "dependencies")
+        );
+        assert_eq!(
+            vec![":library:saved-faces:api".to_string()],
+            block_strings(build, "dependencies")
+        );
+        assert_eq!(
+            vec![
+                "PlatformProvider::class".to_string(),
+                "A::class".to_string()
+            ],
+            array_entries(
+                "@FeatureRoot(dependencies = [PlatformProvider::class, A::class])\n",
+                "FeatureRoot",
+                "dependencies"
+            )
+        );
+    }
+
+    #[test]
+    fn read_declarations() {
+        let subfeature = "@DeclareSubfeature(\n    route = \"home-details\",\n    input = DetailsInput::class,\n)\nobject HomeDetailsSubfeature : Subfeature<VM> {\n    @Composable\n    override fun Screen() {}\n}\n";
+        let entry = "interface HomeFeatureEntry : FeatureEntry {\n    companion object :\n        NavDirection<Map<String, Int>> by createNavDirection(\"home\")\n}\n";
+
+        assert_eq!(
+            Some("\"home-details\"".to_string()),
+            annotation_argument(subfeature, "DeclareSubfeature", "route")
+        );
+        assert_eq!(
+            Some("DetailsInput::class".to_string()),
+            annotation_argument(subfeature, "DeclareSubfeature", "input")
+        );
+        assert_eq!(
+            None,
+            annotation_argument(subfeature, "DeclareSubfeature", "other")
+        );
+        assert_eq!(
+            Some("HomeDetailsSubfeature".to_string()),
+            annotated_declaration(subfeature, "DeclareSubfeature")
+        );
+        assert_eq!(
+            Some(vec!["\"home\"".to_string()]),
+            call_arguments(entry, "createNavDirection")
+        );
+        assert_eq!(
+            Some("Map<String, Int>".to_string()),
+            type_arguments(entry, "NavDirection")
+        );
+    }
+}
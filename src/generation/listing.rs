@@ -0,0 +1,104 @@
+use convert_case::{Case, Casing};
+use serde::Serialize;
+
+use super::file_system::FileSystem;
+use super::gen_context::existing_libraries;
+use super::navigation::NavMap;
+
+/// The feature and library modules of a project
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Listing {
+    pub features: Vec<ListedFeature>,
+    pub libraries: Vec<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ListedFeature {
+    /// Directory of the feature under `feature/`
+    pub name: String,
+    /// Subfeatures in the kebab case `gen-subfeat --screen` takes, e.g. `home-details`
+    pub subfeatures: Vec<String>,
+}
+
+impl Listing {
+    /// Reads the modules of the project in `fs`, subfeatures are found by their
+    /// `@DeclareSubfeature`
+    pub fn load(fs: &dyn FileSystem) -> Self {
+        let features = NavMap::load(fs)
+            .features
+            .into_iter()
+            .map(|feature| ListedFeature {
+                name: feature.name,
+                subfeatures: feature
+                    .subfeatures
+                    .iter()
+                    .map(|subfeature| {
+                        let name = subfeature.name.as_str();
+                        name.strip_suffix("Subfeature")
+                            .unwrap_or(name)
+                            .to_case(Case::Kebab)
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Listing {
+            features,
+            libraries: existing_libraries(fs),
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut lines = vec!["Features:".to_string()];
+        for feature in &self.features {
+            lines.push(format!("  {}", feature.name));
+            for subfeature in &feature.subfeatures {
+                lines.push(format!("    {}", subfeature));
+            }
+        }
+        lines.push("Libraries:".to_string());
+        for library in &self.libraries {
+            lines.push(format!("  {}", library));
+        }
+        lines.join("\n") + "\n"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::file_system::RootedFileSystem;
+
+    #[test]
+    fn list_features_and_libraries() {
+        let root = std::env::temp_dir().join("feature_generator_listing");
+        let _ = std::fs::remove_dir_all(&root);
+        let subfeature = root.join("feature/home/impl/src/main/kotlin/a/subfeature/homedetails");
+        std::fs::create_dir_all(&subfeature).unwrap();
+        std::fs::write(
+            subfeature.join("HomeDetailsSubfeature.kt"),
+            "@DeclareSubfeature(\n    route = \"home-details\",\n)\nobject HomeDetailsSubfeature : Subfeature<A>\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(root.join("library/saved-faces/api")).unwrap();
+
+        let listing = Listing::load(&RootedFileSystem::new(&root));
+
+        assert_eq!(
+            listing,
+            Listing {
+                features: vec![ListedFeature {
+                    name: "home".into(),
+                    subfeatures: vec!["home-details".into()],
+                }],
+                libraries: vec!["saved-faces".into()],
+            }
+        );
+        assert_eq!(
+            listing.render(),
+            "Features:\n  home\n    home-details\nLibraries:\n  saved-faces\n"
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,129 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::file_system::{FileSystem, RealFileSystem};
+use super::history::content_hash;
+use super::HandlebarsContext;
+
+/// Sidecar file recording which template, and which version of it, produced each generated file
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct Manifest {
+    pub files: Vec<GeneratedFile>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct GeneratedFile {
+    pub path: PathBuf,
+    pub template: String,
+    pub version: String,
+    pub context: HandlebarsContext,
+    /// The content as it was rendered, used as the common base when upgrading
+    pub content: String,
+}
+
+impl Manifest {
+    /// Reads the manifest at `path`, an empty one if nothing was generated yet
+    pub fn load(path: &Path) -> Self {
+        Self::load_from(&RealFileSystem, path)
+    }
+
+    /// Like `load`, reading through `fs`
+    pub fn load_from(fs: &dyn FileSystem, path: &Path) -> Self {
+        let Ok(content) = fs.read_to_string(path) else {
+            return Self::default();
+        };
+
+        serde_json::from_str(&content).unwrap_or_else(|error| {
+            panic!(
+                "Couldn't parse generation manifest!\npath: {:?}\n\nDetails\n{}",
+                path, error
+            )
+        })
+    }
+
+    pub fn save(&self, path: &Path) {
+        self.save_to(&RealFileSystem, path)
+    }
+
+    /// Like `save`, writing through `fs`
+    pub fn save_to(&self, fs: &dyn FileSystem, path: &Path) {
+        let content =
+            serde_json::to_string_pretty(self).expect("Manifest should always be serializable");
+        fs.write(path, &(content + "\n")).unwrap_or_else(|_| {
+            panic!(
+                "Error during writing generation manifest!\nFile path: {}",
+                path.display()
+            )
+        });
+    }
+
+    /// Adds `file`, replacing the previous record of the same path
+    pub fn record(&mut self, file: GeneratedFile) {
+        match self.files.iter_mut().find(|f| f.path == file.path) {
+            Some(existing) => *existing = file,
+            None => self.files.push(file),
+        }
+    }
+}
+
+/// Short, stable hash of the given template sources. Each source is prefixed with its length, so
+/// moving text from one source to the next changes the hash.
+pub fn version_of<'a>(sources: impl IntoIterator<Item = &'a str>) -> String {
+    let joined: String = sources
+        .into_iter()
+        .map(|source| format!("{}:{}", source.len(), source))
+        .collect();
+    let mut hash = content_hash(&joined);
+    hash.truncate(12);
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generated_file(path: &str, content: &str) -> GeneratedFile {
+        GeneratedFile {
+            path: path.into(),
+            template: "impl/ImplBuild".into(),
+            version: version_of(["template"]),
+            context: HandlebarsContext::sample(None, None),
+            content: content.into(),
+        }
+    }
+
+    #[test]
+    fn record_replaces_same_path() {
+        let mut manifest = Manifest::default();
+        manifest.record(generated_file("feature/home/impl/build.gradle.kts", "old"));
+        manifest.record(generated_file("feature/home/api/build.gradle.kts", "api"));
+        manifest.record(generated_file("feature/home/impl/build.gradle.kts", "new"));
+
+        assert_eq!(2, manifest.files.len());
+        assert_eq!("new", manifest.files[0].content);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = std::env::temp_dir().join("feature_generator_manifest/manifest.json");
+        let mut manifest = Manifest::default();
+        manifest.record(generated_file(
+            "feature/home/impl/build.gradle.kts",
+            "content",
+        ));
+
+        manifest.save(&path);
+
+        assert_eq!(manifest, Manifest::load(&path));
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn version_changes_with_sources() {
+        assert_eq!(12, version_of(["a"]).len());
+        assert_eq!(version_of(["a", "b"]), version_of(["a", "b"]));
+        assert_ne!(version_of(["a", "b"]), version_of(["a", "c"]));
+        assert_ne!(version_of(["ab", "c"]), version_of(["a", "bc"]));
+    }
+}
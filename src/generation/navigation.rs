@@ -0,0 +1,410 @@
+use std::{collections::BTreeMap, path::Path};
+
+use convert_case::{Case, Casing};
+use serde::Serialize;
+
+use super::file_system::FileSystem;
+use super::gen_context::kotlin_files;
+use super::kotlin;
+
+/// Where a feature can be navigated to, read from its `FeatureEntry`, `@FeatureRoot` and
+/// `@DeclareSubfeature`s
+#[derive(Serialize, Debug, PartialEq)]
+pub struct FeatureRoutes {
+    /// Directory of the feature under `feature/`
+    pub name: String,
+    pub route: Option<String>,
+    pub input: Option<String>,
+    pub start_subfeature: Option<String>,
+    pub subfeatures: Vec<SubfeatureRoute>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SubfeatureRoute {
+    pub name: String,
+    pub route: Option<String>,
+    pub input: Option<String>,
+}
+
+/// A route declared more than once, either by feature entries or by subfeatures
+#[derive(Serialize, Debug, PartialEq)]
+pub struct DuplicateRoute {
+    pub route: String,
+    /// Features, or `feature/Subfeature`s, declaring the route
+    pub declared_by: Vec<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct NavMap {
+    pub features: Vec<FeatureRoutes>,
+    pub duplicates: Vec<DuplicateRoute>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum NavMapFormat {
+    Markdown,
+    Mermaid,
+}
+
+impl FeatureRoutes {
+    fn load(fs: &dyn FileSystem, feature: &Path, name: String) -> Self {
+        let mut routes = FeatureRoutes {
+            name,
+            route: None,
+            input: None,
+            start_subfeature: None,
+            subfeatures: Vec::new(),
+        };
+
+        for source in sources(fs, &feature.join("api/src")) {
+            if let Some(arguments) = kotlin::call_arguments(&source, "createNavDirection") {
+                routes.route = arguments.first().map(|route| unquote(route));
+                routes.input = kotlin::type_arguments(&source, "NavDirection");
+            }
+        }
+
+        for source in sources(fs, &feature.join("impl/src")) {
+            if kotlin::has_annotation(&source, "FeatureRoot") {
+                routes.start_subfeature =
+                    kotlin::annotation_argument(&source, "FeatureRoot", "startSubfeature")
+                        .map(|subfeature| class_name(&subfeature));
+            }
+            if kotlin::has_annotation(&source, "DeclareSubfeature") {
+                let argument =
+                    |argument| kotlin::annotation_argument(&source, "DeclareSubfeature", argument);
+                routes.subfeatures.push(SubfeatureRoute {
+                    name: kotlin::annotated_declaration(&source, "DeclareSubfeature")
+                        .unwrap_or_default(),
+                    route: argument("route").map(|route| unquote(&route)),
+                    input: argument("input").map(|input| class_name(&input)),
+                });
+            }
+        }
+        routes.subfeatures.sort_by(|a, b| a.name.cmp(&b.name));
+        routes
+    }
+
+    fn is_start(&self, subfeature: &SubfeatureRoute) -> bool {
+        self.start_subfeature.as_ref() == Some(&subfeature.name)
+    }
+}
+
+impl NavMap {
+    /// Reads the routes of every module under `feature` in the project in `fs`
+    pub fn load(fs: &dyn FileSystem) -> Self {
+        let features: Vec<FeatureRoutes> = fs
+            .read_dir(Path::new("feature"))
+            .into_iter()
+            .filter(|path| fs.is_dir(path))
+            .filter_map(|path| {
+                let name = path.file_name()?.to_str()?.to_string();
+                Some(FeatureRoutes::load(fs, &path, name))
+            })
+            .collect();
+
+        let mut feature_routes: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        let mut subfeature_routes: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+        for feature in &features {
+            if let Some(route) = &feature.route {
+                feature_routes
+                    .entry(route)
+                    .or_default()
+                    .push(feature.name.clone());
+            }
+            for subfeature in &feature.subfeatures {
+                if let Some(route) = &subfeature.route {
+                    subfeature_routes
+                        .entry(route)
+                        .or_default()
+                        .push(format!("{}/{}", feature.name, subfeature.name));
+                }
+            }
+        }
+        let duplicates = feature_routes
+            .into_iter()
+            .chain(subfeature_routes)
+            .filter(|(_, declared_by)| declared_by.len() > 1)
+            .map(|(route, declared_by)| DuplicateRoute {
+                route: route.to_string(),
+                declared_by,
+            })
+            .collect();
+
+        Self {
+            features,
+            duplicates,
+        }
+    }
+
+    /// Descriptions of the duplicate routes
+    pub fn issues(&self) -> Vec<String> {
+        self.duplicates
+            .iter()
+            .map(|duplicate| {
+                format!(
+                    "duplicate route `{}`: {}",
+                    duplicate.route,
+                    duplicate.declared_by.join(", ")
+                )
+            })
+            .collect()
+    }
+
+    pub fn render(&self, format: NavMapFormat) -> String {
+        match format {
+            NavMapFormat::Markdown => self.to_markdown(),
+            NavMapFormat::Mermaid => self.to_mermaid(),
+        }
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut lines = vec!["# Navigation map".to_string()];
+        for feature in &self.features {
+            lines.push(String::new());
+            lines.push(format!("## {}", feature.name));
+            lines.push(String::new());
+            lines.push(format!(
+                "Route {}, input {}",
+                code(&feature.route),
+                code(&feature.input)
+            ));
+            if feature.subfeatures.is_empty() {
+                continue;
+            }
+            lines.push(String::new());
+            lines.push("| Subfeature | Route | Input |".to_string());
+            lines.push("| --- | --- | --- |".to_string());
+            for subfeature in &feature.subfeatures {
+                let start = if feature.is_start(subfeature) {
+                    " (start)"
+                } else {
+                    ""
+                };
+                lines.push(format!(
+                    "| {}{} | {} | {} |",
+                    subfeature.name,
+                    start,
+                    code(&subfeature.route),
+                    code(&subfeature.input)
+                ));
+            }
+        }
+
+        if !self.duplicates.is_empty() {
+            lines.push(String::new());
+            lines.push("## Duplicate routes".to_string());
+            lines.push(String::new());
+            for duplicate in &self.duplicates {
+                lines.push(format!(
+                    "- `{}`: {}",
+                    duplicate.route,
+                    duplicate.declared_by.join(", ")
+                ));
+            }
+        }
+        lines.join("\n") + "\n"
+    }
+
+    fn to_mermaid(&self) -> String {
+        let mut lines = vec!["graph TD".to_string()];
+        lines.extend(
+            self.issues()
+                .iter()
+                .map(|issue| format!("    %% {}", issue)),
+        );
+        let mut duplicated = Vec::new();
+        for feature in &self.features {
+            let id = feature.name.to_case(Case::Snake);
+            lines.push(format!(
+                "    {}[\"{}<br/>{}\"]",
+                id,
+                feature.name,
+                label(&feature.route, &feature.input)
+            ));
+            if self.is_duplicate(&feature.name) {
+                duplicated.push(id.clone());
+            }
+            for subfeature in &feature.subfeatures {
+                let sub_id = format!("{}_{}", id, subfeature.name.to_case(Case::Snake));
+                lines.push(format!(
+                    "    {}(\"{}<br/>{}\")",
+                    sub_id,
+                    subfeature.name,
+                    label(&subfeature.route, &subfeature.input)
+                ));
+                let arrow = if feature.is_start(subfeature) {
+                    "-->|start|"
+                } else {
+                    "-.->"
+                };
+                lines.push(format!("    {} {} {}", id, arrow, sub_id));
+                if self.is_duplicate(&format!("{}/{}", feature.name, subfeature.name)) {
+                    duplicated.push(sub_id);
+                }
+            }
+        }
+        lines.extend(
+            duplicated
+                .iter()
+                .map(|id| format!("    style {} stroke:red", id)),
+        );
+        lines.join("\n") + "\n"
+    }
+
+    fn is_duplicate(&self, declaration: &str) -> bool {
+        self.duplicates.iter().any(|duplicate| {
+            duplicate
+                .declared_by
+                .iter()
+                .any(|declared| declared == declaration)
+        })
+    }
+}
+
+fn sources(fs: &dyn FileSystem, dir: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    kotlin_files(fs, dir, &mut files);
+    files
+        .into_iter()
+        .filter_map(|path| fs.read_to_string(&path).ok())
+        .collect()
+}
+
+fn unquote(literal: &str) -> String {
+    literal.trim_matches('"').to_string()
+}
+
+/// `EmptyInput` of `EmptyInput::class`
+fn class_name(reference: &str) -> String {
+    reference.trim_end_matches("::class").to_string()
+}
+
+fn code(value: &Option<String>) -> String {
+    value
+        .as_ref()
+        .map_or_else(|| "?".to_string(), |value| format!("`{}`", value))
+}
+
+fn label(route: &Option<String>, input: &Option<String>) -> String {
+    format!(
+        "route: {}<br/>input: {}",
+        route.as_deref().unwrap_or("?"),
+        input.as_deref().unwrap_or("?")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::file_system::RootedFileSystem;
+
+    fn write(root: &Path, path: &str, content: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn load_and_render_nav_map() {
+        let root = std::env::temp_dir().join("feature_generator_navigation");
+        let _ = std::fs::remove_dir_all(&root);
+        for (feature, route) in [("home", "home"), ("profile", "home")] {
+            let pascal = feature.to_case(Case::Pascal);
+            write(
+                &root,
+                &format!("feature/{}/api/src/main/kotlin/a/{}FeatureEntry.kt", feature, pascal),
+                &format!(
+                    "interface {0}FeatureEntry : FeatureEntry {{\n    companion object :\n        NavDirection<EmptyInput> by createNavDirection(\"{1}\")\n}}\n",
+                    pascal, route
+                ),
+            );
+            write(
+                &root,
+                &format!("feature/{}/impl/src/main/kotlin/a/root/{}FeatureRoot.kt", feature, pascal),
+                &format!(
+                    "@FeatureRoot(\n    startSubfeature = {0}Subfeature::class,\n)\ninterface {0}FeatureRoot\n",
+                    pascal
+                ),
+            );
+            write(
+                &root,
+                &format!("feature/{}/impl/src/main/kotlin/a/{}Subfeature.kt", feature, pascal),
+                &format!(
+                    "@DeclareSubfeature(\n    route = \"{}\",\n    input = EmptyInput::class,\n)\nobject {}Subfeature\n",
+                    feature, pascal
+                ),
+            );
+        }
+        write(
+            &root,
+            "feature/profile/impl/src/main/kotlin/a/EditSubfeature.kt",
+            "@DeclareSubfeature(route = \"profile\", input = EditInput::class)\nobject EditSubfeature\n",
+        );
+
+        let map = NavMap::load(&RootedFileSystem::new(&root));
+
+        assert_eq!(
+            vec![
+                DuplicateRoute {
+                    route: "home".into(),
+                    declared_by: vec!["home".into(), "profile".into()],
+                },
+                DuplicateRoute {
+                    route: "profile".into(),
+                    declared_by: vec![
+                        "profile/EditSubfeature".into(),
+                        "profile/ProfileSubfeature".into()
+                    ],
+                },
+            ],
+            map.duplicates
+        );
+        assert_eq!(
+            "# Navigation map
+
+## home
+
+Route `home`, input `EmptyInput`
+
+| Subfeature | Route | Input |
+| --- | --- | --- |
+| HomeSubfeature (start) | `home` | `EmptyInput` |
+
+## profile
+
+Route `home`, input `EmptyInput`
+
+| Subfeature | Route | Input |
+| --- | --- | --- |
+| EditSubfeature | `profile` | `EditInput` |
+| ProfileSubfeature (start) | `profile` | `EmptyInput` |
+
+## Duplicate routes
+
+- `home`: home, profile
+- `profile`: profile/EditSubfeature, profile/ProfileSubfeature
+",
+            map.render(NavMapFormat::Markdown)
+        );
+        assert_eq!(
+            "graph TD
+    %% duplicate route `home`: home, profile
+    %% duplicate route `profile`: profile/EditSubfeature, profile/ProfileSubfeature
+    home[\"home<br/>route: home<br/>input: EmptyInput\"]
+    home_home_subfeature(\"HomeSubfeature<br/>route: home<br/>input: EmptyInput\")
+    home -->|start| home_home_subfeature
+    profile[\"profile<br/>route: home<br/>input: EmptyInput\"]
+    profile_edit_subfeature(\"EditSubfeature<br/>route: profile<br/>input: EditInput\")
+    profile -.-> profile_edit_subfeature
+    profile_profile_subfeature(\"ProfileSubfeature<br/>route: profile<br/>input: EmptyInput\")
+    profile -->|start| profile_profile_subfeature
+    style home stroke:red
+    style profile stroke:red
+    style profile_edit_subfeature stroke:red
+    style profile_profile_subfeature stroke:red
+",
+            map.render(NavMapFormat::Mermaid)
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -1,67 +1,125 @@
 use std::{
-    fs::{create_dir_all, read_to_string, File},
-    path::Path,
+    io,
+    path::{Path, PathBuf},
 };
 
 use handlebars::Handlebars;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+use super::file_system::FileSystem;
+use super::kotlin::Edit;
+
+/// Lines added to an existing file, `line` being the 1-based number of the first added line
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Insertion {
+    pub path: PathBuf,
+    pub line: usize,
+    pub lines: Vec<String>,
+    /// Original lines the added ones took the place of, when an entry was added to an existing line
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub replaced: Vec<String>,
+}
+
+/// Lines taken out of an existing file, `line` being the 1-based number of the first removed line
+#[derive(Serialize, Debug, PartialEq, Clone)]
+pub struct Removal {
+    pub path: PathBuf,
+    pub line: usize,
+    pub lines: Vec<String>,
+    /// What is left of the removed lines, when an entry was taken out of a line keeping the others
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub remaining: Vec<String>,
+}
+
+impl From<Insertion> for Removal {
+    fn from(edit: Insertion) -> Self {
+        Removal {
+            path: edit.path,
+            line: edit.line,
+            lines: edit.replaced,
+            remaining: edit.lines,
+        }
+    }
+}
 
 pub fn generate_file(
+    fs: &dyn FileSystem,
     parent: &Path,
     handlebars: &Handlebars,
     handlebars_context: &impl Serialize,
     file_name: &str,
-    template_content: &str,
-) {
-    create_dir_all(parent).unwrap_or_else(|_| {
-        panic!(
-            "{}",
-            format!(
-                "{:?} <-- this directory should either exists, or we should be able to create it",
-                parent,
+    template_name: &str,
+) -> String {
+    let content = render_file(
+        parent,
+        handlebars,
+        handlebars_context,
+        file_name,
+        template_name,
+    );
+
+    fs.write(&parent.join(file_name), &content)
+        .unwrap_or_else(|_| {
+            panic!(
+                "Wasn't able to create file!\nfile: {}\npath: {:?}",
+                file_name, parent
             )
-            .to_string()
-        )
-    });
+        });
 
-    let file = File::create(parent.join(file_name)).unwrap_or_else(|_| {
-        panic!(
-            "Wasn't able to create file!\nfile: {}\npath: {:?}",
-            file_name, parent
-        )
-    });
+    content
+}
 
+/// The content `generate_file` would write, without touching the disk
+pub fn render_file(
+    parent: &Path,
+    handlebars: &Handlebars,
+    handlebars_context: &impl Serialize,
+    file_name: &str,
+    template_name: &str,
+) -> String {
     handlebars
-        .render_template_to_write(template_content, handlebars_context, file)
+        .render(template_name, handlebars_context)
         .unwrap_or_else(|error| {
             panic!(
-                "Couldn't render template!\nfile: {}\npath: {:?}\n\nDetails\n{:?}",
-                file_name, parent, error.desc
+                "Couldn't render template!\ntemplate: {}\nfile: {}\npath: {:?}\n\nDetails\n{}",
+                template_name, file_name, parent, error
             )
-        });
+        })
 }
 
-pub fn append_line_below(file_path: &Path, pattern: &str, line: &str) {
-    let file = read_to_string(file_path).unwrap_or_else(|_e| {
-        panic!(
-            "Error during modifying existing file!\nFile path: {}",
-            file_path
-                .to_str()
-                .expect("Failed to convert path to string, maybe not valid unicode?")
-        )
-    });
-    let mut done = false;
-    let lines: Vec<String> = file.lines().fold(Vec::new(), |mut acc, curr_line| {
-        let curr_line = curr_line.to_string();
-        acc.push(curr_line.clone());
-        if curr_line.contains(pattern) && !done {
-            acc.push(line.to_string());
-            done = true;
-        }
-        acc
-    });
+/// Applies `edit` to `content`, the current content of `file_path`, in memory. Returns the new
+/// content with the same line endings, `None` if there's no edit.
+pub fn apply_edit(
+    file_path: &Path,
+    content: String,
+    edit: impl FnOnce(&str) -> Option<Edit>,
+) -> Option<(String, Insertion)> {
+    let file = TextFile::parse(content);
+    let edit = edit(&file.content)?;
+    let mut lines: Vec<&str> = file.content.lines().collect();
+    lines.splice(
+        edit.index..edit.index + edit.removed.len(),
+        edit.inserted.iter().map(String::as_str),
+    );
+
+    Some((
+        file.join(&lines),
+        Insertion {
+            path: file_path.to_path_buf(),
+            line: edit.index + 1,
+            lines: edit.inserted,
+            replaced: edit.removed,
+        },
+    ))
+}
 
-    std::fs::remove_file(file_path).unwrap_or_else(|_| {
+/// Applies the `edit` computed from the content of `file_path`, nothing is changed if there's none
+pub fn edit_file(
+    fs: &dyn FileSystem,
+    file_path: &Path,
+    edit: impl FnOnce(&str) -> Option<Edit>,
+) -> Option<Insertion> {
+    let content = fs.read_to_string(file_path).unwrap_or_else(|_e| {
         panic!(
             "Error during modifying existing file!\nFile path: {}",
             file_path
@@ -69,7 +127,9 @@ pub fn append_line_below(file_path: &Path, pattern: &str, line: &str) {
                 .expect("Failed to convert path to string, maybe not valid unicode?")
         )
     });
-    std::fs::write(file_path, lines.join("\n") + "\n").unwrap_or_else(|_| {
+    let (content, insertion) = apply_edit(file_path, content, edit)?;
+
+    fs.write(file_path, &content).unwrap_or_else(|_| {
         panic!(
             "Error during modifying existing file!\nFile path: {}",
             file_path
@@ -77,45 +137,50 @@ pub fn append_line_below(file_path: &Path, pattern: &str, line: &str) {
                 .expect("Failed to convert path to string, maybe not valid unicode?")
         )
     });
+
+    Some(insertion)
 }
 
-pub fn add_line_to_file(file_path: &Path, line: &str) {
-    let file = read_to_string(file_path).unwrap_or_else(|_| {
-        panic!(
-            "Could not read file {}",
-            file_path
-                .to_str()
-                .expect("Should be able to convert path to string")
-        )
-    });
-    let mut lines: Vec<&str> = file.lines().collect();
-    lines.push(line);
+/// Removes the lines of `insertion`, looking first where they were inserted, then anywhere else
+/// in the file, and puts back the lines they replaced. Returns the 1-based line they were removed
+/// from, `None` if they weren't found.
+pub fn remove_insertion(fs: &dyn FileSystem, insertion: &Insertion) -> Option<usize> {
+    let file = TextFile::parse(fs.read_to_string(&insertion.path).ok()?);
+    let mut lines: Vec<&str> = file.content.lines().collect();
+    let count = insertion.lines.len();
+    let matches_at = |index: usize| {
+        lines
+            .get(index..index + count)
+            .is_some_and(|candidate| candidate == insertion.lines.as_slice())
+    };
 
-    std::fs::remove_file(file_path).unwrap_or_else(|_| {
-        panic!(
-            "Error during modifying existing file!\nFile path: {}",
-            file_path
-                .to_str()
-                .expect("Failed to convert path to string, maybe not valid unicode?")
-        )
-    });
-    std::fs::write(file_path, lines.join("\n") + "\n").unwrap_or_else(|_| {
-        panic!(
-            "Error during modifying existing file!\nFile path: {}",
-            file_path
-                .to_str()
-                .expect("Failed to convert path to string, maybe not valid unicode?")
-        )
-    });
+    let index = if insertion.line > 0 && matches_at(insertion.line - 1) {
+        insertion.line - 1
+    } else {
+        (0..lines.len()).find(|index| matches_at(*index))?
+    };
+    lines.splice(
+        index..index + count,
+        insertion.replaced.iter().map(String::as_str),
+    );
+
+    fs.write(&insertion.path, &file.join(&lines))
+        .unwrap_or_else(|_| {
+            panic!(
+                "Error during modifying existing file!\nFile path: {}",
+                insertion.path.display()
+            )
+        });
+    Some(index + 1)
 }
 
-pub fn overwrite_file_at_path(file_path: &Path, lines: Vec<String>) {
-    let parent_file = file_path.parent().unwrap_or_else(|| {
-        panic!("Couldn't find parent path in path: {:?}", file_path,);
-    });
-    create_dir_all(parent_file).unwrap();
-    let _ = std::fs::remove_file(file_path);
-    std::fs::write(file_path, lines.join("\n") + "\n").unwrap_or_else(|_| {
+pub fn overwrite_file_at_path(fs: &dyn FileSystem, file_path: &Path, lines: Vec<String>) {
+    let file = fs
+        .read_to_string(file_path)
+        .map(TextFile::parse)
+        .unwrap_or_default();
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+    fs.write(file_path, &file.join(&lines)).unwrap_or_else(|_| {
         panic!(
             "Error during modifying existing file!\nFile path: {}",
             file_path
@@ -124,3 +189,154 @@ pub fn overwrite_file_at_path(file_path: &Path, lines: Vec<String>) {
         )
     });
 }
+
+/// Writes `content` to a temporary file next to `path`, then renames it over `path`, so a crash
+/// never leaves a half written file behind. The permissions of the replaced file are kept.
+pub fn write_atomically(path: &Path, content: &str) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = path.with_file_name(temp_name);
+
+    let result = std::fs::write(&temp_path, content)
+        .and_then(|_| match std::fs::metadata(path) {
+            Ok(metadata) => std::fs::set_permissions(&temp_path, metadata.permissions()),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(error),
+        })
+        .and_then(|_| std::fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// An existing text file, remembering its layout so edits can be written back the same way
+struct TextFile {
+    /// The content without the byte order mark
+    content: String,
+    bom: bool,
+    line_ending: &'static str,
+    final_newline: bool,
+}
+
+impl Default for TextFile {
+    fn default() -> Self {
+        Self {
+            content: String::new(),
+            bom: false,
+            line_ending: "\n",
+            final_newline: true,
+        }
+    }
+}
+
+impl TextFile {
+    const BOM: &'static str = "\u{feff}";
+
+    fn parse(content: String) -> Self {
+        let (bom, content) = match content.strip_prefix(Self::BOM) {
+            Some(stripped) => (true, stripped.to_string()),
+            None => (false, content),
+        };
+        let line_ending = match content.find('\n') {
+            Some(index) if content[..index].ends_with('\r') => "\r\n",
+            _ => "\n",
+        };
+
+        Self {
+            bom,
+            line_ending,
+            final_newline: content.is_empty() || content.ends_with('\n'),
+            content,
+        }
+    }
+
+    /// `lines` laid out like this file
+    fn join(&self, lines: &[&str]) -> String {
+        let mut content = String::new();
+        if self.bom {
+            content.push_str(Self::BOM);
+        }
+        content.push_str(&lines.join(self.line_ending));
+        if self.final_newline && !lines.is_empty() {
+            content.push_str(self.line_ending);
+        }
+        content
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::file_system::RealFileSystem;
+    use std::fs::{create_dir_all, read_to_string};
+
+    fn temp_file(name: &str, content: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("feature_generator_render");
+        create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn insert_second_line(_source: &str) -> Option<Edit> {
+        Some(Edit {
+            index: 1,
+            removed: Vec::new(),
+            inserted: vec!["include(\":b\")".into()],
+        })
+    }
+
+    #[test]
+    fn edit_keeps_line_endings_and_bom() {
+        let path = temp_file(
+            "crlf.gradle.kts",
+            "\u{feff}include(\":a\")\r\ninclude(\":c\")\r\n",
+        );
+
+        let insertion = edit_file(&RealFileSystem, &path, insert_second_line).unwrap();
+
+        assert_eq!(
+            "\u{feff}include(\":a\")\r\ninclude(\":b\")\r\ninclude(\":c\")\r\n",
+            read_to_string(&path).unwrap()
+        );
+        remove_insertion(&RealFileSystem, &insertion);
+        assert_eq!(
+            "\u{feff}include(\":a\")\r\ninclude(\":c\")\r\n",
+            read_to_string(&path).unwrap()
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn edit_keeps_missing_final_newline() {
+        let path = temp_file("no_newline.gradle.kts", "include(\":a\")\ninclude(\":c\")");
+
+        edit_file(&RealFileSystem, &path, insert_second_line);
+
+        assert_eq!(
+            "include(\":a\")\ninclude(\":b\")\ninclude(\":c\")",
+            read_to_string(&path).unwrap()
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn edit_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_file("gradlew.kts", "include(\":a\")\n");
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        edit_file(&RealFileSystem, &path, insert_second_line);
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(0o755, mode & 0o777);
+        std::fs::remove_file(path).unwrap();
+    }
+}
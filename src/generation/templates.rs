@@ -0,0 +1,647 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use handlebars::Handlebars;
+use serde::Serialize;
+use similar::TextDiff;
+
+use super::file_system::FileSystem;
+use super::manifest;
+use crate::args_parser;
+
+pub const API_FEATURE_ENTRY: &str = "api/FeatureEntry";
+pub const API_BUILD: &str = "api/ApiBuild";
+pub const API_BUILD_GROOVY: &str = "api/ApiBuildGroovy";
+pub const IMPL_BUILD: &str = "impl/ImplBuild";
+pub const IMPL_BUILD_GROOVY: &str = "impl/ImplBuildGroovy";
+pub const FEATURE_ROOT: &str = "impl/root/FeatureRoot";
+pub const SUBFEATURE: &str = "impl/firstpage/Subfeature";
+pub const PAGE_SCREEN: &str = "impl/firstpage/screen/FirstPageScreen";
+pub const PAGE_VIEW_MODEL: &str = "impl/firstpage/screen/FirstPageViewModel";
+pub const LIB_API_BUILD: &str = "lib/api/LibApiBuild";
+pub const LIB_API_BUILD_GROOVY: &str = "lib/api/LibApiBuildGroovy";
+pub const LIB_PROVIDER: &str = "lib/api/Provider";
+pub const GET_EXAMPLE: &str = "lib/api/GetExample";
+pub const LIB_IMPL_BUILD: &str = "lib/impl/build.gradle.kts";
+pub const LIB_IMPL_BUILD_GROOVY: &str = "lib/impl/build.gradle";
+pub const LIB_DECL: &str = "lib/impl/LibDecl";
+pub const GET_EXAMPLE_USE_CASE: &str = "lib/impl/GetExampleUseCase.kt";
+
+/// Templates shipped with the generator, keyed by their path relative to `src/templates/`
+/// without the `.handlebars` extension
+pub const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    (
+        API_FEATURE_ENTRY,
+        include_str!("../templates/api/FeatureEntry.handlebars"),
+    ),
+    (
+        API_BUILD,
+        include_str!("../templates/api/ApiBuild.handlebars"),
+    ),
+    (
+        API_BUILD_GROOVY,
+        include_str!("../templates/api/ApiBuildGroovy.handlebars"),
+    ),
+    (
+        IMPL_BUILD,
+        include_str!("../templates/impl/ImplBuild.handlebars"),
+    ),
+    (
+        IMPL_BUILD_GROOVY,
+        include_str!("../templates/impl/ImplBuildGroovy.handlebars"),
+    ),
+    (
+        FEATURE_ROOT,
+        include_str!("../templates/impl/root/FeatureRoot.handlebars"),
+    ),
+    (
+        SUBFEATURE,
+        include_str!("../templates/impl/firstpage/Subfeature.handlebars"),
+    ),
+    (
+        PAGE_SCREEN,
+        include_str!("../templates/impl/firstpage/screen/FirstPageScreen.handlebars"),
+    ),
+    (
+        PAGE_VIEW_MODEL,
+        include_str!("../templates/impl/firstpage/screen/FirstPageViewModel.handlebars"),
+    ),
+    (
+        LIB_API_BUILD,
+        include_str!("../templates/lib/api/LibApiBuild.handlebars"),
+    ),
+    (
+        LIB_API_BUILD_GROOVY,
+        include_str!("../templates/lib/api/LibApiBuildGroovy.handlebars"),
+    ),
+    (
+        LIB_PROVIDER,
+        include_str!("../templates/lib/api/Provider.handlebars"),
+    ),
+    (
+        GET_EXAMPLE,
+        include_str!("../templates/lib/api/GetExample.handlebars"),
+    ),
+    (
+        LIB_IMPL_BUILD,
+        include_str!("../templates/lib/impl/build.gradle.kts.handlebars"),
+    ),
+    (
+        LIB_IMPL_BUILD_GROOVY,
+        include_str!("../templates/lib/impl/build.gradle.handlebars"),
+    ),
+    (
+        LIB_DECL,
+        include_str!("../templates/lib/impl/LibDecl.handlebars"),
+    ),
+    (
+        GET_EXAMPLE_USE_CASE,
+        include_str!("../templates/lib/impl/GetExampleUseCase.kt.handlebars"),
+    ),
+];
+
+/// Partials shipped with the generator, usable from any template as `{{> name}}`
+pub const BUILTIN_PARTIALS: &[(&str, &str)] = &[
+    (
+        "file_header",
+        include_str!("../templates/partials/file_header.handlebars"),
+    ),
+    (
+        "compose_imports",
+        include_str!("../templates/partials/compose_imports.handlebars"),
+    ),
+];
+
+const TEMPLATE_EXTENSION: &str = "handlebars";
+pub const PARTIALS_DIR: &str = "partials";
+
+/// A template or partial, either built-in or read from an override directory
+#[derive(Debug, PartialEq, Clone)]
+pub struct TemplateSource {
+    pub name: String,
+    pub content: String,
+    /// Where the template was read from, `None` for built-in templates
+    pub path: Option<PathBuf>,
+}
+
+impl TemplateSource {
+    fn builtin(name: &str, content: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            content: content.to_string(),
+            path: None,
+        }
+    }
+
+    pub fn display_path(&self) -> String {
+        match &self.path {
+            Some(path) => path.display().to_string(),
+            None => format!("builtin:{}.{}", self.name, TEMPLATE_EXTENSION),
+        }
+    }
+}
+
+/// Directories where users can override templates, from lowest to highest priority. The global
+/// one is absolute, the local one relative to the project root.
+pub fn override_dirs() -> Vec<PathBuf> {
+    vec![
+        args_parser::get_global_templates_path(),
+        args_parser::get_local_templates_path(),
+    ]
+}
+
+/// Built-in templates, each replaced by the highest priority override with the same name
+pub fn templates(fs: &dyn FileSystem) -> Vec<TemplateSource> {
+    let overrides: Vec<TemplateSource> = override_dirs()
+        .iter()
+        .flat_map(|dir| templates_in(fs, dir, dir))
+        .collect();
+
+    resolve(BUILTIN_TEMPLATES, overrides)
+}
+
+/// Built-in partials, each replaced by the highest priority override with the same name,
+/// plus the partials that only exist in override directories
+pub fn partials(fs: &dyn FileSystem) -> Vec<TemplateSource> {
+    let overrides: Vec<TemplateSource> = override_dirs()
+        .iter()
+        .flat_map(|dir| templates_in(fs, &dir.join(PARTIALS_DIR), &dir.join(PARTIALS_DIR)))
+        .collect();
+
+    let mut partials = resolve(BUILTIN_PARTIALS, overrides.clone());
+    for partial in overrides.into_iter().rev() {
+        if !partials
+            .iter()
+            .any(|existing| existing.name == partial.name)
+        {
+            partials.push(partial);
+        }
+    }
+    partials
+}
+
+/// Override files which don't correspond to any built-in template, most likely typos
+pub fn unknown_overrides(fs: &dyn FileSystem) -> Vec<TemplateSource> {
+    override_dirs()
+        .iter()
+        .flat_map(|dir| templates_in(fs, dir, dir))
+        .filter(|template| {
+            !BUILTIN_TEMPLATES
+                .iter()
+                .any(|(name, _)| *name == template.name)
+        })
+        .collect()
+}
+
+/// Built-in templates and partials, with partials named `partials/<name>` to match the layout
+/// of the template directories
+pub fn builtin_sources() -> Vec<TemplateSource> {
+    BUILTIN_TEMPLATES
+        .iter()
+        .map(|(name, content)| TemplateSource::builtin(name, content))
+        .chain(BUILTIN_PARTIALS.iter().map(|(name, content)| {
+            TemplateSource::builtin(&format!("{}/{}", PARTIALS_DIR, name), content)
+        }))
+        .collect()
+}
+
+/// Templates and partials found in the template directory `dir`, named like [builtin_sources]
+pub fn overrides_in(fs: &dyn FileSystem, dir: &Path) -> Vec<TemplateSource> {
+    let partials_dir = dir.join(PARTIALS_DIR);
+    let mut overrides = templates_in(fs, dir, dir);
+    overrides.extend(
+        templates_in(fs, &partials_dir, &partials_dir)
+            .into_iter()
+            .map(|mut partial| {
+                partial.name = format!("{}/{}", PARTIALS_DIR, partial.name);
+                partial
+            }),
+    );
+    overrides
+}
+
+/// Path of the template called `name` inside the template directory `dir`
+pub fn override_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.{}", name, TEMPLATE_EXTENSION))
+}
+
+/// Writes `source` into the template directory `dir`, keeping the built-in layout.
+/// Returns `false` without writing if the file already exists and `force` isn't set.
+pub fn eject(fs: &dyn FileSystem, source: &TemplateSource, dir: &Path, force: bool) -> bool {
+    let path = override_path(dir, &source.name);
+    if fs.exists(&path) && !force {
+        return false;
+    }
+
+    fs.write(&path, &source.content)
+        .unwrap_or_else(|_| panic!("Wasn't able to write template!\npath: {:?}", path));
+    true
+}
+
+/// Unified diff from `builtin` to `custom`, `None` if their contents are the same
+pub fn diff(builtin: &TemplateSource, custom: &TemplateSource) -> Option<String> {
+    if builtin.content == custom.content {
+        return None;
+    }
+
+    let diff = TextDiff::from_lines(&builtin.content, &custom.content)
+        .unified_diff()
+        .header(&builtin.display_path(), &custom.display_path())
+        .to_string();
+    Some(diff)
+}
+
+/// Version of `template`, which changes whenever the template or a partial it uses, directly or
+/// through other partials, changes
+pub fn version(template: &TemplateSource, partials: &[TemplateSource]) -> String {
+    let find = |name: &str| partials.iter().find(|partial| partial.name == name);
+    let mut used = BTreeSet::new();
+    let mut pending = vec![template.content.as_str()];
+    while let Some(content) = pending.pop() {
+        for (_, name) in referenced_partials(content) {
+            if let Some(partial) = find(&name) {
+                if used.insert(name) {
+                    pending.push(&partial.content);
+                }
+            }
+        }
+    }
+
+    let partial_contents = used
+        .iter()
+        .filter_map(|name| find(name).map(|partial| partial.content.as_str()));
+    manifest::version_of(std::iter::once(template.content.as_str()).chain(partial_contents))
+}
+
+/// The templates and partials of a project, read once. Rendering and the versions recorded in the
+/// manifest both come from it, so they can't disagree.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TemplateSet {
+    pub templates: Vec<TemplateSource>,
+    pub partials: Vec<TemplateSource>,
+}
+
+impl TemplateSet {
+    /// The built-ins, replaced by the overrides found through `fs`
+    pub fn load(fs: &dyn FileSystem) -> Self {
+        Self {
+            templates: templates(fs),
+            partials: partials(fs),
+        }
+    }
+
+    /// Version of every template, by name
+    pub fn versions(&self) -> BTreeMap<String, String> {
+        self.templates
+            .iter()
+            .map(|template| (template.name.clone(), version(template, &self.partials)))
+            .collect()
+    }
+
+    /// Registers every partial and template, so templates can be rendered by name
+    pub fn register(&self, handlebars: &mut Handlebars) {
+        for partial in &self.partials {
+            handlebars
+                .register_partial(&partial.name, &partial.content)
+                .unwrap_or_else(|error| {
+                    panic!(
+                        "Invalid partial!\npath: {}\n\nDetails\n{}",
+                        partial.display_path(),
+                        error
+                    )
+                });
+        }
+
+        for template in &self.templates {
+            handlebars
+                .register_template_string(&template.name, &template.content)
+                .unwrap_or_else(|error| {
+                    panic!(
+                        "Invalid template!\npath: {}\n\nDetails\n{}",
+                        template.display_path(),
+                        error
+                    )
+                });
+        }
+    }
+}
+
+fn resolve(builtins: &[(&str, &str)], overrides: Vec<TemplateSource>) -> Vec<TemplateSource> {
+    builtins
+        .iter()
+        .map(|(name, content)| {
+            overrides
+                .iter()
+                .rev()
+                .find(|template| template.name == *name)
+                .cloned()
+                .unwrap_or_else(|| TemplateSource::builtin(name, content))
+        })
+        .collect()
+}
+
+fn templates_in(fs: &dyn FileSystem, dir: &Path, root: &Path) -> Vec<TemplateSource> {
+    let mut templates = Vec::new();
+    for path in fs.read_dir(dir) {
+        if fs.is_dir(&path) {
+            if dir == root && path.file_name().is_some_and(|name| name == PARTIALS_DIR) {
+                continue;
+            }
+            templates.extend(templates_in(fs, &path, root));
+            continue;
+        }
+
+        if path
+            .extension()
+            .is_none_or(|extension| extension != TEMPLATE_EXTENSION)
+        {
+            continue;
+        }
+
+        let Some(name) = path
+            .strip_prefix(root)
+            .ok()
+            .map(|relative| relative.with_extension(""))
+            .and_then(|relative| relative.to_str().map(|name| name.replace('\\', "/")))
+        else {
+            continue;
+        };
+        let content = fs
+            .read_to_string(&path)
+            .unwrap_or_else(|_| panic!("Couldn't read template {:?}", path));
+
+        templates.push(TemplateSource {
+            name,
+            content,
+            path: Some(path),
+        });
+    }
+    templates
+}
+
+/// A problem found while checking templates
+#[derive(Debug, PartialEq)]
+pub struct TemplateIssue {
+    pub file: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl Display for TemplateIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "{}:{}:{}: {}", self.file, line, column, self.message)
+            }
+            (Some(line), None) => write!(f, "{}:{}: {}", self.file, line, self.message),
+            _ => write!(f, "{}: {}", self.file, self.message),
+        }
+    }
+}
+
+/// Renders every template in strict mode against `sample_context`, and reports syntax
+/// errors, undefined variables, unknown helpers and missing partials
+pub fn check(
+    templates: &[TemplateSource],
+    partials: &[TemplateSource],
+    mut register_helpers: impl FnMut(&mut Handlebars),
+    sample_context: &impl Serialize,
+) -> Vec<TemplateIssue> {
+    let mut handlebars = Handlebars::new();
+    handlebars.set_strict_mode(true);
+    register_helpers(&mut handlebars);
+
+    let mut issues = Vec::new();
+    for source in partials.iter().chain(templates) {
+        if let Err(error) = handlebars.register_template_string(&source.name, &source.content) {
+            issues.push(TemplateIssue {
+                file: source.display_path(),
+                line: error.line_no,
+                column: error.column_no,
+                message: error.reason().to_string(),
+            });
+        }
+    }
+
+    for source in partials.iter().chain(templates) {
+        for (line, partial) in referenced_partials(&source.content) {
+            if !handlebars.has_template(&partial) {
+                issues.push(TemplateIssue {
+                    file: source.display_path(),
+                    line: Some(line),
+                    column: None,
+                    message: format!("Partial \"{}\" is not defined", partial),
+                });
+            }
+        }
+    }
+
+    for source in templates {
+        if !handlebars.has_template(&source.name) {
+            continue;
+        }
+
+        if let Err(error) = handlebars.render(&source.name, sample_context) {
+            let file = error
+                .template_name
+                .as_ref()
+                .and_then(|name| partials.iter().find(|partial| &partial.name == name))
+                .map(|partial| partial.display_path())
+                .unwrap_or_else(|| source.display_path());
+
+            issues.push(TemplateIssue {
+                file,
+                line: error.line_no,
+                column: error.column_no,
+                message: error.desc,
+            });
+        }
+    }
+
+    issues
+}
+
+fn referenced_partials(content: &str) -> Vec<(usize, String)> {
+    content
+        .lines()
+        .enumerate()
+        .flat_map(|(index, line)| {
+            line.match_indices("{{>").map(move |(start, _)| {
+                let name: String = line[start + 3..]
+                    .trim_start()
+                    .chars()
+                    .take_while(|c| !c.is_whitespace() && *c != '}')
+                    .collect();
+                (index + 1, name)
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::generation::file_system::MemoryFileSystem;
+    use crate::generation::register_helpers;
+
+    fn source(name: &str, content: &str) -> TemplateSource {
+        TemplateSource::builtin(name, content)
+    }
+
+    fn sample_context() -> serde_json::Value {
+        json!({
+            "base_package": "com.example",
+            "app": "sample-app",
+            "module": "sample-feature",
+            "first_page": "sample-page",
+            "used_libraries": ["sample-library"],
+            "navigation_targets": ["sample-target"],
+        })
+    }
+
+    #[test]
+    fn builtin_templates_are_valid() {
+        let templates: Vec<TemplateSource> = BUILTIN_TEMPLATES
+            .iter()
+            .map(|(name, content)| source(name, content))
+            .collect();
+        let partials: Vec<TemplateSource> = BUILTIN_PARTIALS
+            .iter()
+            .map(|(name, content)| source(name, content))
+            .collect();
+
+        let issues = check(&templates, &partials, register_helpers, &sample_context());
+
+        assert_eq!(Vec::<TemplateIssue>::new(), issues);
+    }
+
+    #[test]
+    fn check_reports_undefined_variables_and_helpers() {
+        let templates = vec![
+            source("typo", "package test\n\n{{ pascal modul }}"),
+            source("helper", "{{ shout module }}"),
+        ];
+
+        let issues = check(&templates, &[], register_helpers, &sample_context());
+
+        assert_eq!(2, issues.len(), "{:?}", issues);
+        assert_eq!(
+            "builtin:typo.handlebars:3:1: Variable \"modul\" not found in strict mode.",
+            issues[0].to_string()
+        );
+        assert!(issues[1].message.contains("shout"), "{}", issues[1]);
+    }
+
+    #[test]
+    fn check_reports_syntax_errors_and_missing_partials() {
+        let templates = vec![
+            source("syntax", "line\n{{#if module}}\n"),
+            source("partial", "{{> license}}\npackage test\n"),
+        ];
+
+        let issues = check(&templates, &[], register_helpers, &sample_context());
+
+        assert_eq!(2, issues.len());
+        assert_eq!("builtin:syntax.handlebars", issues[0].file);
+        assert!(issues[0].line.is_some());
+        assert_eq!(
+            "builtin:partial.handlebars:1: Partial \"license\" is not defined",
+            issues[1].to_string()
+        );
+    }
+
+    #[test]
+    fn overrides_replace_builtins() {
+        let overrides = vec![TemplateSource {
+            name: IMPL_BUILD.to_string(),
+            content: "custom".to_string(),
+            path: Some(PathBuf::from("impl/ImplBuild.handlebars")),
+        }];
+
+        let resolved = resolve(BUILTIN_TEMPLATES, overrides);
+
+        let impl_build = resolved.iter().find(|t| t.name == IMPL_BUILD).unwrap();
+        assert_eq!("custom", impl_build.content);
+        assert_eq!(BUILTIN_TEMPLATES.len(), resolved.len());
+    }
+
+    #[test]
+    fn version_covers_used_partials() {
+        let template = source("template", "{{> file_header}}\npackage test\n");
+        let header = source("file_header", "");
+        let changed_header = source("file_header", "// License\n");
+        let unused = source("compose_imports", "import a\n");
+
+        assert_eq!(
+            version(&template, std::slice::from_ref(&header)),
+            version(&template, &[header.clone(), unused])
+        );
+        assert_ne!(
+            version(&template, &[header]),
+            version(&template, &[changed_header])
+        );
+    }
+
+    #[test]
+    fn version_covers_nested_partials() {
+        let template = source("template", "{{> file_header}}\npackage test\n");
+        let header = source("file_header", "{{> license}}\n");
+        let license = source("license", "// MIT\n");
+        let changed_license = source("license", "// GPL\n");
+
+        assert_ne!(
+            version(&template, &[header.clone(), license]),
+            version(&template, &[header, changed_license])
+        );
+    }
+
+    #[test]
+    fn eject_and_diff() {
+        let fs = MemoryFileSystem::new();
+        let dir = Path::new(".feature_generator/templates");
+        let builtin = builtin_sources()
+            .into_iter()
+            .find(|source| source.name == "partials/file_header")
+            .unwrap();
+
+        assert!(eject(&fs, &builtin, dir, false));
+        assert!(!eject(&fs, &builtin, dir, false));
+
+        let ejected = overrides_in(&fs, dir);
+        assert_eq!(1, ejected.len());
+        assert_eq!(builtin.name, ejected[0].name);
+        assert_eq!(None, diff(&builtin, &ejected[0]));
+
+        fs.write(&override_path(dir, &builtin.name), "// License\n")
+            .unwrap();
+        let changed = &overrides_in(&fs, dir)[0];
+        let diff = diff(&builtin, changed).unwrap();
+        assert!(diff.contains("+// License"), "{}", diff);
+        assert!(
+            diff.contains("--- builtin:partials/file_header.handlebars"),
+            "{}",
+            diff
+        );
+        assert_eq!("// License\n", partials(&fs)[0].content);
+    }
+
+    #[test]
+    fn templates_in_dir() {
+        let dir = Path::new("templates");
+        let fs = MemoryFileSystem::new()
+            .with_file(dir.join("impl/ImplBuild.handlebars"), "custom")
+            .with_file(dir.join("partials/license.handlebars"), "// License")
+            .with_file(dir.join("notes.txt"), "ignored");
+
+        let templates = templates_in(&fs, dir, dir);
+
+        assert_eq!(1, templates.len());
+        assert_eq!(IMPL_BUILD, templates[0].name);
+    }
+}
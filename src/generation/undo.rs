@@ -0,0 +1,149 @@
+use std::path::PathBuf;
+
+use super::file_system::FileSystem;
+use super::history::{content_hash, History, HistoryEntry};
+use super::manifest::Manifest;
+use super::render::{self, Insertion};
+
+#[derive(Debug, PartialEq)]
+pub enum UndoAction {
+    /// A created file was deleted
+    Deleted(PathBuf),
+    /// A created file was kept, because it changed since generation
+    KeptModified(PathBuf),
+    /// A created file was already gone
+    AlreadyDeleted(PathBuf),
+    /// Inserted lines were removed, starting at the given line
+    LinesRemoved(Insertion, usize),
+    /// Inserted lines couldn't be found anymore, they were most likely edited
+    LinesNotFound(Insertion),
+}
+
+/// Reverts the most recent generation in `history`, and forgets about it in both `history` and
+/// `manifest`. Returns the reverted entry and what was done, `None` if there's nothing to undo.
+pub fn undo(
+    fs: &dyn FileSystem,
+    history: &mut History,
+    manifest: &mut Manifest,
+) -> Option<(HistoryEntry, Vec<UndoAction>)> {
+    let entry = history.entries.pop()?;
+    let mut actions = Vec::new();
+
+    for insertion in entry.insertions.iter().rev() {
+        actions.push(match render::remove_insertion(fs, insertion) {
+            Some(line) => UndoAction::LinesRemoved(insertion.clone(), line),
+            None => UndoAction::LinesNotFound(insertion.clone()),
+        });
+    }
+
+    for file in &entry.created {
+        let Ok(content) = fs.read_to_string(&file.path) else {
+            actions.push(UndoAction::AlreadyDeleted(file.path.clone()));
+            continue;
+        };
+
+        if content_hash(&content) != file.hash {
+            actions.push(UndoAction::KeptModified(file.path.clone()));
+            continue;
+        }
+
+        fs.remove_file(&file.path).unwrap_or_else(|_| {
+            panic!(
+                "Wasn't able to delete generated file!\nFile path: {}",
+                file.path.display()
+            )
+        });
+        manifest
+            .files
+            .retain(|generated| generated.path != file.path);
+        actions.push(UndoAction::Deleted(file.path.clone()));
+    }
+
+    Some((entry, actions))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::file_system::MemoryFileSystem;
+    use crate::generation::gen_context::{AppCtx, Library};
+    use crate::generation::history::CreatedFile;
+
+    #[test]
+    fn undo_empty_history() {
+        assert_eq!(
+            None,
+            undo(
+                &MemoryFileSystem::new(),
+                &mut History::default(),
+                &mut Manifest::default()
+            )
+        );
+    }
+
+    #[test]
+    fn undo_last_generation() {
+        let module = PathBuf::from("library/saved-faces/api");
+        let unchanged = module.join("build.gradle.kts");
+        let modified = module.join("Provider.kt");
+        let settings = PathBuf::from("settings.gradle.kts");
+        let fs = MemoryFileSystem::new()
+            .with_file(&unchanged, "plugins {}\n")
+            .with_file(&modified, "interface Provider { edited }\n")
+            .with_file(
+                &settings,
+                "include(\":app\")\ninclude(\":library:saved-faces:api\")\ninclude(\":theme\")\n",
+            );
+
+        let context = Library {
+            app_context: AppCtx {
+                is_testing: false,
+                app_name: "my-app".into(),
+                base_package: "com.my.app".into(),
+            },
+            library_name: "saved-faces".into(),
+            apps: Vec::new(),
+        };
+        let insertion = Insertion {
+            path: settings.clone(),
+            line: 3,
+            lines: vec!["include(\":library:saved-faces:api\")".into()],
+            replaced: Vec::new(),
+        };
+        let mut history = History {
+            entries: vec![HistoryEntry::new(
+                context.into(),
+                vec![
+                    CreatedFile {
+                        path: unchanged.clone(),
+                        hash: content_hash("plugins {}\n"),
+                    },
+                    CreatedFile {
+                        path: modified.clone(),
+                        hash: content_hash("interface Provider\n"),
+                    },
+                ],
+                vec![insertion.clone()],
+            )],
+        };
+
+        let (entry, actions) = undo(&fs, &mut history, &mut Manifest::default()).unwrap();
+
+        assert_eq!("gen-lib saved-faces", entry.command);
+        assert!(history.entries.is_empty());
+        assert_eq!(
+            vec![
+                UndoAction::LinesRemoved(insertion, 2),
+                UndoAction::Deleted(unchanged.clone()),
+                UndoAction::KeptModified(modified.clone()),
+            ],
+            actions
+        );
+        assert!(!fs.exists(&unchanged));
+        assert!(fs.exists(&modified));
+        assert_eq!(
+            "include(\":app\")\ninclude(\":theme\")\n",
+            fs.read_to_string(&settings).unwrap()
+        );
+    }
+}
@@ -0,0 +1,281 @@
+use std::path::PathBuf;
+
+use similar::{capture_diff_slices, Algorithm, DiffTag};
+
+use super::file_system::FileSystem;
+use super::manifest::Manifest;
+use super::new_handlebars;
+use super::templates::TemplateSet;
+
+const CONFLICT_START: &str = "<<<<<<< local\n";
+const CONFLICT_SEPARATOR: &str = "=======\n";
+const CONFLICT_END: &str = ">>>>>>> template\n";
+
+#[derive(Debug, PartialEq)]
+pub enum UpgradeStatus {
+    /// The file wasn't edited since it was generated, so it was simply re-rendered
+    Rerendered,
+    /// Local edits and template changes were merged without conflicts
+    Merged,
+    /// Conflict markers were written at the given lines, they need manual resolution
+    Conflicts(Vec<usize>),
+    /// The generated file doesn't exist anymore
+    Missing,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct UpgradeResult {
+    pub path: PathBuf,
+    pub template: String,
+    pub status: UpgradeStatus,
+}
+
+/// Re-renders every file in `manifest` whose template changed since generation, merging the
+/// changes into the local edits. The manifest is updated to point at the new versions.
+pub fn upgrade(fs: &dyn FileSystem, manifest: &mut Manifest, dry_run: bool) -> Vec<UpgradeResult> {
+    let templates = TemplateSet::load(fs);
+    let handlebars = new_handlebars(&templates);
+    let versions = templates.versions();
+    let mut results = Vec::new();
+
+    for file in manifest.files.iter_mut() {
+        let Some(version) = versions.get(&file.template) else {
+            continue;
+        };
+        if *version == file.version {
+            continue;
+        }
+
+        let Ok(local) = fs.read_to_string(&file.path) else {
+            results.push(UpgradeResult {
+                path: file.path.clone(),
+                template: file.template.clone(),
+                status: UpgradeStatus::Missing,
+            });
+            continue;
+        };
+
+        let rendered = handlebars
+            .render(&file.template, &file.context)
+            .unwrap_or_else(|error| {
+                panic!(
+                    "Couldn't render template!\ntemplate: {}\nfile: {:?}\n\nDetails\n{}",
+                    file.template, file.path, error
+                )
+            });
+
+        let (content, status) = if local == file.content {
+            (rendered.clone(), UpgradeStatus::Rerendered)
+        } else {
+            let merge = merge(&file.content, &local, &rendered);
+            let status = if merge.conflicts.is_empty() {
+                UpgradeStatus::Merged
+            } else {
+                UpgradeStatus::Conflicts(merge.conflicts)
+            };
+            (merge.content, status)
+        };
+
+        if !dry_run {
+            fs.write(&file.path, &content).unwrap_or_else(|_| {
+                panic!(
+                    "Error during modifying existing file!\nFile path: {}",
+                    file.path.display()
+                )
+            });
+            file.version = version.clone();
+            file.content = rendered;
+        }
+
+        results.push(UpgradeResult {
+            path: file.path.clone(),
+            template: file.template.clone(),
+            status,
+        });
+    }
+
+    results
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Merge {
+    pub content: String,
+    /// 1-based line numbers of the conflict markers in `content`
+    pub conflicts: Vec<usize>,
+}
+
+/// A change replacing `base[start..end]` with `lines`
+#[derive(Debug)]
+struct Hunk<'a> {
+    start: usize,
+    end: usize,
+    lines: Vec<&'a str>,
+}
+
+/// Line based three-way merge of the `local` and `template` versions of `base`.
+/// Overlapping changes which differ are kept as conflicts between git style markers.
+pub fn merge(base: &str, local: &str, template: &str) -> Merge {
+    let base: Vec<&str> = base.split_inclusive('\n').collect();
+    let local: Vec<&str> = local.split_inclusive('\n').collect();
+    let template: Vec<&str> = template.split_inclusive('\n').collect();
+    let local_hunks = hunks(&base, &local);
+    let template_hunks = hunks(&base, &template);
+
+    let mut lines: Vec<&str> = Vec::new();
+    let mut conflicts = Vec::new();
+    let (mut position, mut l, mut t) = (0, 0, 0);
+
+    while l < local_hunks.len() || t < template_hunks.len() {
+        let local_first = t >= template_hunks.len()
+            || (l < local_hunks.len() && local_hunks[l].start <= template_hunks[t].start);
+        let first = if local_first {
+            &local_hunks[l]
+        } else {
+            &template_hunks[t]
+        };
+        let (start, mut end) = (first.start, first.end);
+        let (local_from, template_from) = (l, t);
+        if local_first {
+            l += 1;
+        } else {
+            t += 1;
+        }
+
+        loop {
+            if l < local_hunks.len() && local_hunks[l].start <= end {
+                end = end.max(local_hunks[l].end);
+                l += 1;
+            } else if t < template_hunks.len() && template_hunks[t].start <= end {
+                end = end.max(template_hunks[t].end);
+                t += 1;
+            } else {
+                break;
+            }
+        }
+
+        lines.extend(&base[position..start]);
+        let local_side = apply(&base, start, end, &local_hunks[local_from..l]);
+        let template_side = apply(&base, start, end, &template_hunks[template_from..t]);
+        if local_from == l {
+            lines.extend(template_side);
+        } else if template_from == t || local_side == template_side {
+            lines.extend(local_side);
+        } else {
+            conflicts.push(lines.len() + 1);
+            lines.push(CONFLICT_START);
+            lines.extend(ensure_newline(local_side));
+            lines.push(CONFLICT_SEPARATOR);
+            lines.extend(ensure_newline(template_side));
+            lines.push(CONFLICT_END);
+        }
+        position = end;
+    }
+    lines.extend(&base[position..]);
+
+    Merge {
+        content: lines.concat(),
+        conflicts,
+    }
+}
+
+fn hunks<'a>(base: &[&str], other: &[&'a str]) -> Vec<Hunk<'a>> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for op in capture_diff_slices(Algorithm::Myers, base, other) {
+        let (tag, old, new) = op.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            continue;
+        }
+
+        match hunks.last_mut() {
+            Some(last) if last.end == old.start => {
+                last.end = old.end;
+                last.lines.extend(&other[new]);
+            }
+            _ => hunks.push(Hunk {
+                start: old.start,
+                end: old.end,
+                lines: other[new].to_vec(),
+            }),
+        }
+    }
+    hunks
+}
+
+fn apply<'a>(base: &[&'a str], start: usize, end: usize, hunks: &[Hunk<'a>]) -> Vec<&'a str> {
+    let mut lines = Vec::new();
+    let mut position = start;
+    for hunk in hunks {
+        lines.extend(&base[position..hunk.start]);
+        lines.extend(&hunk.lines);
+        position = hunk.end;
+    }
+    lines.extend(&base[position..end]);
+    lines
+}
+
+/// Conflict markers have to start on their own line, even if the side ends the file
+fn ensure_newline(mut lines: Vec<&str>) -> Vec<&str> {
+    if lines.last().is_some_and(|last| !last.ends_with('\n')) {
+        lines.push("\n");
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str =
+        "plugins {\n    id(\"android\")\n}\n\ndependencies {\n    implementation(a)\n}\n";
+
+    #[test]
+    fn merge_without_local_changes_takes_template() {
+        let template = BASE.replace("implementation(a)", "implementation(b)");
+
+        let merge = merge(BASE, BASE, &template);
+
+        assert_eq!(template, merge.content);
+        assert!(merge.conflicts.is_empty());
+    }
+
+    #[test]
+    fn merge_keeps_independent_changes() {
+        let local = BASE.replace(
+            "    id(\"android\")\n",
+            "    id(\"android\")\n    id(\"ksp\")\n",
+        );
+        let template = BASE.replace("implementation(a)", "implementation(b)");
+
+        let merge = merge(BASE, &local, &template);
+
+        assert_eq!(
+            "plugins {\n    id(\"android\")\n    id(\"ksp\")\n}\n\ndependencies {\n    implementation(b)\n}\n",
+            merge.content
+        );
+        assert!(merge.conflicts.is_empty());
+    }
+
+    #[test]
+    fn merge_same_change_on_both_sides() {
+        let changed = BASE.replace("implementation(a)", "implementation(b)");
+
+        let merge = merge(BASE, &changed, &changed);
+
+        assert_eq!(changed, merge.content);
+        assert!(merge.conflicts.is_empty());
+    }
+
+    #[test]
+    fn merge_reports_conflicts() {
+        let local = BASE.replace("implementation(a)", "implementation(local)");
+        let template = BASE.replace("implementation(a)", "implementation(template)");
+
+        let merge = merge(BASE, &local, &template);
+
+        assert_eq!(
+            "plugins {\n    id(\"android\")\n}\n\ndependencies {\n<<<<<<< local\n    implementation(local)\n=======\n    implementation(template)\n>>>>>>> template\n}\n",
+            merge.content
+        );
+        assert_eq!(vec![6], merge.conflicts);
+    }
+}
@@ -1,88 +1,404 @@
 use convert_case::{Case, Casing};
-use handlebars::{Context, Handlebars, Helper, HelperResult, JsonRender, Output, RenderContext};
+use handlebars::{
+    Context, Handlebars, Helper, HelperResult, JsonRender, Output, RenderContext, RenderError,
+    Renderable,
+};
 
 pub fn to_flat(
     h: &Helper,
-    _: &Handlebars,
+    r: &Handlebars,
     _: &Context,
     _: &mut RenderContext,
     out: &mut dyn Output,
 ) -> HelperResult {
-    let param = h.param(0).unwrap();
-
-    out.write(
-        param
-            .value()
-            .render()
-            .to_string()
-            .to_case(Case::Flat)
-            .as_ref(),
-    )?;
-    Ok(())
+    write_case(h, r, out, Case::Flat)
 }
 
 pub fn to_pascal(
     h: &Helper,
-    _: &Handlebars,
+    r: &Handlebars,
     _: &Context,
     _: &mut RenderContext,
     out: &mut dyn Output,
 ) -> HelperResult {
-    let param = h.param(0).unwrap();
-
-    out.write(
-        param
-            .value()
-            .render()
-            .to_string()
-            .to_case(Case::Pascal)
-            .as_ref(),
-    )?;
-    Ok(())
+    write_case(h, r, out, Case::Pascal)
 }
 
 pub fn to_camel(
     h: &Helper,
-    _: &Handlebars,
+    r: &Handlebars,
     _: &Context,
     _: &mut RenderContext,
     out: &mut dyn Output,
 ) -> HelperResult {
-    let param = h.param(0).unwrap();
-
-    out.write(
-        param
-            .value()
-            .render()
-            .to_string()
-            .to_case(Case::Camel)
-            .as_ref(),
-    )?;
-    Ok(())
+    write_case(h, r, out, Case::Camel)
 }
 
 pub fn to_kebab(
     h: &Helper,
-    _: &Handlebars,
+    r: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    write_case(h, r, out, Case::Kebab)
+}
+
+pub fn to_snake(
+    h: &Helper,
+    r: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    write_case(h, r, out, Case::Snake)
+}
+
+pub fn to_screaming_snake(
+    h: &Helper,
+    r: &Handlebars,
     _: &Context,
     _: &mut RenderContext,
     out: &mut dyn Output,
 ) -> HelperResult {
-    let param = h.param(0).unwrap();
-
-    out.write(
-        param
-            .value()
-            .render()
-            .to_string()
-            .to_case(Case::Kebab)
-            .as_ref(),
-    )?;
+    write_case(h, r, out, Case::ScreamingSnake)
+}
+
+pub fn to_title(
+    h: &Helper,
+    r: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    write_case(h, r, out, Case::Title)
+}
+
+/// Turns a package name into a path, e.g. `com.example.app` into `com/example/app`
+pub fn to_package_path(
+    h: &Helper,
+    r: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let param = required_param(h, r, 0)?;
+
+    out.write(&param.replace('.', "/"))?;
+    Ok(())
+}
+
+pub fn to_plural(
+    h: &Helper,
+    r: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let param = required_param(h, r, 0)?;
+
+    out.write(&pluralize(&param))?;
+    Ok(())
+}
+
+pub fn to_singular(
+    h: &Helper,
+    r: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let param = required_param(h, r, 0)?;
+
+    out.write(&singularize(&param))?;
+    Ok(())
+}
+
+/// Writes all parameters one after the other, e.g. `{{ concat module "Provider" }}`
+pub fn concat(
+    h: &Helper,
+    r: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    out: &mut dyn Output,
+) -> HelperResult {
+    required_param(h, r, 0)?;
+
+    for param in h.params() {
+        out.write(&param.value().render())?;
+    }
+    Ok(())
+}
+
+/// Renders its block when the two parameters are equal, and the `{{else}}` block otherwise.
+/// Used as a subexpression it evaluates to a truthy or falsy value.
+pub fn eq<'reg, 'rc>(
+    h: &Helper<'reg, 'rc>,
+    r: &'reg Handlebars<'reg>,
+    ctx: &'rc Context,
+    rc: &mut RenderContext<'reg, 'rc>,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let lhs = required_param(h, r, 0)?;
+    let rhs = required_param(h, r, 1)?;
+
+    render_condition(lhs == rhs, h, r, ctx, rc, out)
+}
+
+/// Renders its block when the parameter is falsy, and the `{{else}}` block otherwise.
+/// Used as a subexpression it evaluates to a truthy or falsy value.
+pub fn not<'reg, 'rc>(
+    h: &Helper<'reg, 'rc>,
+    r: &'reg Handlebars<'reg>,
+    ctx: &'rc Context,
+    rc: &mut RenderContext<'reg, 'rc>,
+    out: &mut dyn Output,
+) -> HelperResult {
+    let param = h.param(0).ok_or_else(|| missing_param(h, 0))?;
+    let value = param.value();
+    let is_truthy = !value.is_null()
+        && value != &serde_json::Value::Bool(false)
+        && value.render() != ""
+        && value.as_array().is_none_or(|array| !array.is_empty());
+
+    render_condition(!is_truthy, h, r, ctx, rc, out)
+}
+
+fn render_condition<'reg, 'rc>(
+    condition: bool,
+    h: &Helper<'reg, 'rc>,
+    r: &'reg Handlebars<'reg>,
+    ctx: &'rc Context,
+    rc: &mut RenderContext<'reg, 'rc>,
+    out: &mut dyn Output,
+) -> HelperResult {
+    if !h.is_block() {
+        if condition {
+            out.write("true")?;
+        }
+        return Ok(());
+    }
+
+    let template = if condition { h.template() } else { h.inverse() };
+    match template {
+        Some(template) => template.render(r, ctx, rc, out),
+        None => Ok(()),
+    }
+}
+
+fn write_case(h: &Helper, r: &Handlebars, out: &mut dyn Output, case: Case) -> HelperResult {
+    let param = required_param(h, r, 0)?;
+
+    out.write(param.to_case(case).as_ref())?;
     Ok(())
 }
 
+/// Rendered value of the parameter at `index`. Strict mode doesn't cover helper parameters,
+/// so a typo like `{{ pascal modul }}` is reported here instead.
+fn required_param(h: &Helper, r: &Handlebars, index: usize) -> Result<String, RenderError> {
+    let param = h.param(index).ok_or_else(|| missing_param(h, index))?;
+    if r.strict_mode() && param.is_value_missing() {
+        return Err(RenderError::strict_error(param.relative_path()));
+    }
+
+    Ok(param.value().render())
+}
+
+fn missing_param(h: &Helper, index: usize) -> RenderError {
+    RenderError::new(format!(
+        "Helper \"{}\" is missing its parameter at position {}",
+        h.name(),
+        index
+    ))
+}
+
+/// Plural of the singular `word`, words ending in `s` get `es` like `status` and `bus`
+fn pluralize(word: &str) -> String {
+    if ["s", "sh", "ch", "x", "z"]
+        .iter()
+        .any(|suffix| strip_suffix_ignore_case(word, suffix).is_some())
+    {
+        return format!("{}es", word);
+    }
+
+    if let Some(stem) = strip_suffix_ignore_case(word, "y") {
+        if !stem.to_lowercase().ends_with(['a', 'e', 'i', 'o', 'u']) {
+            return format!("{}ies", stem);
+        }
+    }
+
+    format!("{}s", word)
+}
+
+/// Singular of the plural `word`, the inverse of `pluralize`
+fn singularize(word: &str) -> String {
+    if let Some(stem) = strip_suffix_ignore_case(word, "ies") {
+        return format!("{}y", stem);
+    }
+
+    if let Some(stem) = strip_suffix_ignore_case(word, "es") {
+        let ends_in_single_s = strip_suffix_ignore_case(stem, "s").is_some()
+            && strip_suffix_ignore_case(stem, "ss").is_none();
+        if ends_in_single_s
+            || ["ss", "sh", "ch", "x", "z"]
+                .iter()
+                .any(|suffix| strip_suffix_ignore_case(stem, suffix).is_some())
+        {
+            return stem.to_string();
+        }
+    }
+
+    if let Some(stem) = strip_suffix_ignore_case(word, "s") {
+        if strip_suffix_ignore_case(stem, "s").is_none() {
+            return stem.to_string();
+        }
+    }
+
+    word.to_string()
+}
+
+/// `word` without the ASCII `suffix`, compared ignoring case
+fn strip_suffix_ignore_case<'a>(word: &'a str, suffix: &str) -> Option<&'a str> {
+    let cut = word.len().checked_sub(suffix.len())?;
+    if !word.is_char_boundary(cut) || !word[cut..].eq_ignore_ascii_case(suffix) {
+        return None;
+    }
+
+    Some(&word[..cut])
+}
+
 #[cfg(test)]
 mod tests {
+    use handlebars::Handlebars;
+    use serde_json::json;
+
+    use super::{pluralize, singularize};
+    use crate::generation::register_helpers;
+
+    fn render(template: &str, data: serde_json::Value) -> Result<String, String> {
+        let mut handlebars = Handlebars::new();
+        register_helpers(&mut handlebars);
+        handlebars
+            .render_template(template, &data)
+            .map_err(|error| error.desc)
+    }
+
+    #[test]
+    fn case_helpers() {
+        let data = json!({ "module": "meme-search" });
+
+        assert_eq!(
+            Ok("meme_search".into()),
+            render("{{ snake module }}", data.clone())
+        );
+        assert_eq!(
+            Ok("MEME_SEARCH".into()),
+            render("{{ screaming_snake module }}", data.clone())
+        );
+        assert_eq!(Ok("Meme Search".into()), render("{{ title module }}", data));
+    }
+
     #[test]
-    fn todo() {}
+    fn package_path() {
+        assert_eq!(
+            Ok("com/example/app".into()),
+            render(
+                "{{ package_path pkg }}",
+                json!({ "pkg": "com.example.app" })
+            )
+        );
+    }
+
+    #[test]
+    fn plural_and_singular() {
+        let cases = [
+            ("face", "faces"),
+            ("search", "searches"),
+            ("category", "categories"),
+            ("day", "days"),
+            ("box", "boxes"),
+        ];
+
+        for (singular, plural) in cases {
+            assert_eq!(
+                Ok(plural.to_string()),
+                render("{{ plural word }}", json!({ "word": singular }))
+            );
+            assert_eq!(
+                Ok(singular.to_string()),
+                render("{{ singular word }}", json!({ "word": plural }))
+            );
+        }
+
+        for (singular, plural) in [
+            ("status", "statuses"),
+            ("bus", "buses"),
+            ("canvas", "canvases"),
+            ("alias", "aliases"),
+        ] {
+            assert_eq!(
+                Ok(plural.to_string()),
+                render("{{ plural word }}", json!({ "word": singular }))
+            );
+            assert_eq!(
+                Ok(singular.to_string()),
+                render("{{ singular word }}", json!({ "word": plural }))
+            );
+        }
+    }
+
+    #[test]
+    fn singular_inverts_plural() {
+        for word in ["status", "bus", "box", "story", "class", "face"] {
+            assert_eq!(word, singularize(&pluralize(word)));
+        }
+    }
+
+    #[test]
+    fn plural_and_singular_keep_non_ascii_words() {
+        assert_eq!("Straße", singularize("Straßes"));
+        assert_eq!("İ", singularize("İS"));
+        assert_eq!("Café", singularize(&pluralize("Café")));
+    }
+
+    #[test]
+    fn concat() {
+        assert_eq!(
+            Ok("HomeProvider".into()),
+            render(
+                "{{ concat (pascal module) \"Provider\" }}",
+                json!({ "module": "home" })
+            )
+        );
+    }
+
+    #[test]
+    fn eq_and_not_blocks() {
+        let template = "{{#eq kind \"lib\"}}library{{else}}feature{{/eq}}";
+        assert_eq!(
+            Ok("library".into()),
+            render(template, json!({ "kind": "lib" }))
+        );
+        assert_eq!(
+            Ok("feature".into()),
+            render(template, json!({ "kind": "feat" }))
+        );
+
+        let template = "{{#not page}}none{{else}}{{ page }}{{/not}}";
+        assert_eq!(Ok("none".into()), render(template, json!({ "page": null })));
+        assert_eq!(
+            Ok("home".into()),
+            render(template, json!({ "page": "home" }))
+        );
+
+        let template = "{{#if (eq kind \"lib\")}}yes{{else}}no{{/if}}";
+        assert_eq!(Ok("no".into()), render(template, json!({ "kind": "feat" })));
+    }
+
+    #[test]
+    fn missing_param_is_render_error() {
+        let error = render("{{ pascal }}", json!({})).unwrap_err();
+
+        assert!(error.contains("\"pascal\""), "{}", error);
+    }
 }
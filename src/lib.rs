@@ -1,6 +1,10 @@
 pub mod args_parser;
+pub mod completion;
 pub mod generation;
 pub mod helpers;
+pub mod output;
+pub mod server;
+pub mod wizard;
 
 #[cfg(test)]
 mod tests {
@@ -1,23 +1,500 @@
-use std::process::exit;
+use std::{io, process::exit};
 
-use feature_generator::args_parser;
-use feature_generator::generation::{self, Generator};
+use clap::CommandFactory;
+use feature_generator::args_parser::{self, Cli, Command, TemplatesCommand};
+use feature_generator::completion;
+use feature_generator::generation::dependency::{self, Dependency};
+use feature_generator::generation::file_system::FileSystem;
+use feature_generator::generation::gen_context::{AppCtx, CtxCreationError};
+use feature_generator::generation::graph::ModuleGraph;
+use feature_generator::generation::history::History;
+use feature_generator::generation::listing::Listing;
+use feature_generator::generation::manifest::Manifest;
+use feature_generator::generation::navigation::NavMap;
+use feature_generator::generation::undo::{self, UndoAction};
+use feature_generator::generation::upgrade::{self, UpgradeStatus};
+use feature_generator::generation::{self, templates, Generator, HandlebarsContext};
+use feature_generator::output::{self, Modification, Output};
+use feature_generator::server::Server;
+use feature_generator::wizard;
+use serde_json::json;
 
 fn main() {
     let args = args_parser::parse_args();
     if args.is_none() {
         exit(0);
     }
+    let args =
+        args.expect("Command should be something other than generate-completions at this point");
+    let fs = args.file_system();
 
-    match Generator::from_cli(args.expect("Command should be something other than generate-completions at this point")) {
-        Ok(generator) => generator.generate(),
-        Err(err) => match err {
-            generation::gen_context::CtxCreationError::AppNameMissing => print!(
-                "App name is missing from config, please run `feature_generator config` first"
-            ),
-            generation::gen_context::CtxCreationError::BasePackageNameMissing => print!(
-                "App base package is missing from config, please run `feature_generator config` first"
-            ),
-        },
+    if let Command::Complete { words } = &args.command {
+        for candidate in completion::complete(Cli::command(), words, &fs, &args.app_targets) {
+            println!("{}", candidate);
+        }
+        exit(0);
+    }
+
+    if let Command::Serve { .. } = &args.command {
+        let server = Server::new(args.clone());
+        server
+            .serve(io::stdin().lock(), io::stdout().lock())
+            .expect("Couldn't read requests or write responses");
+        exit(0);
+    }
+
+    let mut out = Output::new(args.output, args.command.name());
+    if out.is_json() {
+        output::install_panic_hook(args.command.name());
+    }
+
+    if let Command::Templates { command } = &args.command {
+        exit(run_templates_command(command, &args, out));
+    }
+
+    if let Command::Upgrade { dry_run } = &args.command {
+        exit(run_upgrade(&fs, *dry_run, out));
+    }
+
+    if let Command::Undo = &args.command {
+        exit(run_undo(&fs, out));
+    }
+
+    if let Command::Graph { format } = &args.command {
+        let graph = ModuleGraph::load(&fs);
+        if out.is_json() {
+            out.data(&graph);
+        } else {
+            print!("{}", graph.render(*format));
+        }
+        for issue in graph.issues() {
+            if !out.is_json() {
+                eprintln!("warning: {}", issue);
+            }
+            out.warning(issue);
+        }
+        exit(out.finish(0));
+    }
+
+    if let Command::NavMap { format } = &args.command {
+        let map = NavMap::load(&fs);
+        if out.is_json() {
+            out.data(&map);
+        } else {
+            print!("{}", map.render(*format));
+        }
+        for issue in map.issues() {
+            if !out.is_json() {
+                eprintln!("warning: {}", issue);
+            }
+            out.warning(issue);
+        }
+        exit(out.finish(0));
+    }
+
+    if let Command::List = &args.command {
+        let listing = Listing::load(&fs);
+        out.text(listing.render().trim_end());
+        out.data(&listing);
+        exit(out.finish(0));
+    }
+
+    if let Command::New {
+        kind,
+        name,
+        feature,
+        yes,
+    } = &args.command
+    {
+        exit(wizard::run(
+            &args,
+            *kind,
+            name.clone(),
+            feature.clone(),
+            *yes,
+            out,
+        ));
+    }
+
+    if let Command::AddDep {
+        feature,
+        dependency,
+    }
+    | Command::RemoveDep {
+        feature,
+        dependency,
+    } = &args.command
+    {
+        let add = matches!(&args.command, Command::AddDep { .. });
+        exit(run_dependency_command(
+            &args,
+            feature,
+            &dependency.into(),
+            add,
+            out,
+        ));
+    }
+
+    let exit_code = match Generator::from_cli(args) {
+        Ok(generator) => {
+            out.plan(generator.generate());
+            0
+        }
+        Err(err) => {
+            if !out.is_json() {
+                print!("{}", err);
+            }
+            out.error(err.code(), err);
+            1
+        }
+    };
+    exit(out.finish(exit_code));
+}
+
+fn run_templates_command(command: &TemplatesCommand, cli: &Cli, mut out: Output) -> i32 {
+    let fs = cli.file_system();
+    match command {
+        TemplatesCommand::Check => {
+            let templates = templates::templates(&fs);
+            let partials = templates::partials(&fs);
+            let context = HandlebarsContext::sample(cli.base_package.clone(), cli.app_name.clone());
+            let mut issues = templates::check(
+                &templates,
+                &partials,
+                generation::register_helpers,
+                &context,
+            );
+            for unknown in templates::unknown_overrides(&fs) {
+                issues.push(templates::TemplateIssue {
+                    file: unknown.display_path(),
+                    line: None,
+                    column: None,
+                    message: "Doesn't override any built-in template".to_string(),
+                });
+            }
+
+            for issue in &issues {
+                out.text(issue);
+                out.error("template_issue", issue);
+            }
+            out.data(&json!({
+                "templates": templates.len(),
+                "partials": partials.len(),
+            }));
+            if issues.is_empty() {
+                out.text(format!(
+                    "Checked {} templates and {} partials, no issues found",
+                    templates.len(),
+                    partials.len()
+                ));
+                out.finish(0)
+            } else {
+                out.text(format!("{} issue(s) found", issues.len()));
+                out.finish(1)
+            }
+        }
+        TemplatesCommand::Eject { name, all, force } => {
+            let dir = args_parser::get_local_templates_path();
+            let sources: Vec<_> = templates::builtin_sources()
+                .into_iter()
+                .filter(|source| *all || name.as_ref() == Some(&source.name))
+                .collect();
+            if sources.is_empty() {
+                return unknown_template(name.as_deref().unwrap_or_default(), out);
+            }
+
+            for source in sources {
+                let path = templates::override_path(&dir, &source.name);
+                if templates::eject(&fs, &source, &dir, *force) {
+                    out.text(format!("Ejected {}", path.display()));
+                    out.report.created.push(path);
+                } else {
+                    let message = format!(
+                        "Skipped {}, it already exists. Use --force to overwrite it",
+                        path.display()
+                    );
+                    out.text(&message);
+                    out.warning(message);
+                }
+            }
+            out.finish(0)
+        }
+        TemplatesCommand::Diff { name } => {
+            let builtins = templates::builtin_sources();
+            if let Some(name) = name {
+                if !builtins.iter().any(|builtin| &builtin.name == name) {
+                    return unknown_template(name, out);
+                }
+            }
+            let overrides: Vec<_> = templates::override_dirs()
+                .iter()
+                .flat_map(|dir| templates::overrides_in(&fs, dir))
+                .collect();
+            let mut diffs = Vec::new();
+            for custom in overrides
+                .iter()
+                .filter(|custom| name.is_none() || name.as_ref() == Some(&custom.name))
+            {
+                let Some(builtin) = builtins.iter().find(|b| b.name == custom.name) else {
+                    continue;
+                };
+                if let Some(diff) = templates::diff(builtin, custom) {
+                    if !out.is_json() {
+                        print!("{}", diff);
+                    }
+                    diffs.push(json!({ "template": custom.name, "diff": diff }));
+                }
+            }
+
+            if diffs.is_empty() {
+                out.text("Project templates are the same as the built-in ones");
+            }
+            out.data(&diffs);
+            out.finish(0)
+        }
+    }
+}
+
+/// Reports that no built-in template is called `name`, listing the available ones
+fn unknown_template(name: &str, mut out: Output) -> i32 {
+    out.text(format!(
+        "Unknown template \"{}\", available templates:",
+        name
+    ));
+    let available: Vec<_> = templates::builtin_sources()
+        .into_iter()
+        .map(|source| source.name)
+        .collect();
+    for source in &available {
+        out.text(format!("  {}", source));
+    }
+    out.error("unknown_template", format!("Unknown template \"{}\"", name));
+    out.data(&json!({ "available": available }));
+    out.finish(1)
+}
+
+fn run_upgrade(fs: &dyn FileSystem, dry_run: bool, mut out: Output) -> i32 {
+    let manifest_path = args_parser::get_manifest_path();
+    let mut manifest = Manifest::load_from(fs, &manifest_path);
+    let results = upgrade::upgrade(fs, &mut manifest, dry_run);
+    if !dry_run {
+        manifest.save_to(fs, &manifest_path);
+    }
+
+    if results.is_empty() {
+        out.text("Every generated file is up to date");
+        out.data(&json!([]));
+        return out.finish(0);
+    }
+
+    let mut has_conflicts = false;
+    let mut upgraded = Vec::new();
+    for result in &results {
+        let path = result.path.display();
+        let (status, conflicts) = match &result.status {
+            UpgradeStatus::Rerendered => {
+                out.text(format!("upgraded   {} ({})", path, result.template));
+                ("upgraded", &[][..])
+            }
+            UpgradeStatus::Merged => {
+                out.text(format!("merged     {} ({})", path, result.template));
+                ("merged", &[][..])
+            }
+            UpgradeStatus::Missing => {
+                out.text(format!("missing    {} ({})", path, result.template));
+                out.warning(format!("{} is missing", path));
+                ("missing", &[][..])
+            }
+            UpgradeStatus::Conflicts(lines) => {
+                has_conflicts = true;
+                for line in lines {
+                    out.text(format!(
+                        "conflict   {}:{} ({})",
+                        path, line, result.template
+                    ));
+                    out.error("merge_conflict", format!("{}:{}", path, line));
+                }
+                ("conflicts", &lines[..])
+            }
+        };
+        upgraded.push(json!({
+            "path": result.path,
+            "template": result.template,
+            "status": status,
+            "conflict_lines": conflicts,
+        }));
+    }
+    out.data(&upgraded);
+
+    if has_conflicts {
+        out.text("Resolve the conflicts marked with <<<<<<< local and >>>>>>> template");
+        out.finish(1)
+    } else {
+        out.finish(0)
+    }
+}
+
+fn run_dependency_command(
+    cli: &Cli,
+    feature: &str,
+    dependency: &Dependency,
+    add: bool,
+    mut out: Output,
+) -> i32 {
+    let app_ctx = cli
+        .app_name
+        .clone()
+        .ok_or(CtxCreationError::AppNameMissing)
+        .and_then(|app_name| {
+            Ok(AppCtx {
+                is_testing: cli.debug,
+                app_name,
+                base_package: cli
+                    .base_package
+                    .clone()
+                    .ok_or(CtxCreationError::BasePackageNameMissing)?,
+            })
+        });
+    let app_ctx = match app_ctx {
+        Ok(app_ctx) => app_ctx,
+        Err(err) => {
+            if !out.is_json() {
+                print!("{}", err);
+            }
+            out.error(err.code(), err);
+            return out.finish(1);
+        }
+    };
+    let (name, result) = match dependency {
+        Dependency::Library(name) | Dependency::FeatureApi(name) => (
+            name,
+            if add {
+                dependency::add_dependency(&cli.file_system(), &app_ctx, feature, dependency).map(
+                    |insertions| {
+                        insertions
+                            .into_iter()
+                            .map(Modification::from)
+                            .collect::<Vec<_>>()
+                    },
+                )
+            } else {
+                dependency::remove_dependency(&cli.file_system(), &app_ctx, feature, dependency)
+                    .map(|removals| removals.into_iter().map(Modification::from).collect())
+            },
+        ),
+    };
+
+    match result {
+        Ok(changes) if changes.is_empty() => {
+            if add {
+                let message = format!("`{}` already depends on `{}`", feature, name);
+                out.text(&message);
+                out.warning(message);
+                out.finish(0)
+            } else {
+                let message = format!("`{}` doesn't depend on `{}`", feature, name);
+                out.text(&message);
+                out.error("not_a_dependency", message);
+                out.finish(1)
+            }
+        }
+        Ok(changes) => {
+            for change in changes {
+                let (action, lines) = if add {
+                    ("added  ", &change.inserted)
+                } else {
+                    ("removed", &change.removed)
+                };
+                out.text(format!(
+                    "{}    {}:{}",
+                    action,
+                    change.path.display(),
+                    change.line
+                ));
+                for line in lines {
+                    out.text(format!("    {}", line.trim()));
+                }
+                out.report.modified.push(change);
+            }
+            out.finish(0)
+        }
+        Err(err) => {
+            let message = match &err {
+                dependency::DependencyError::UnknownFeature(name) => {
+                    format!("Feature `{}` doesn't exist", name)
+                }
+                dependency::DependencyError::UnknownLibrary(name) => {
+                    format!("Library `{}` doesn't exist", name)
+                }
+                dependency::DependencyError::MissingFile(path) => {
+                    format!("{} doesn't exist, nothing was changed", path.display())
+                }
+            };
+            out.text(&message);
+            out.error(err.code(), message);
+            out.finish(1)
+        }
     }
 }
+
+fn run_undo(fs: &dyn FileSystem, mut out: Output) -> i32 {
+    let history_path = args_parser::get_history_path();
+    let manifest_path = args_parser::get_manifest_path();
+    let mut history = History::load_from(fs, &history_path);
+    let mut manifest = Manifest::load_from(fs, &manifest_path);
+
+    let Some((entry, actions)) = undo::undo(fs, &mut history, &mut manifest) else {
+        out.text("Nothing to undo");
+        out.warning("Nothing to undo");
+        return out.finish(0);
+    };
+    history.save_to(fs, &history_path);
+    manifest.save_to(fs, &manifest_path);
+
+    out.text(format!("Reverted `{}`", entry.command));
+    out.data(&json!({ "reverted": entry.command }));
+    for action in actions {
+        match action {
+            UndoAction::Deleted(path) => {
+                out.text(format!("deleted    {}", path.display()));
+                out.report.deleted.push(path);
+            }
+            UndoAction::AlreadyDeleted(path) => {
+                out.text(format!("missing    {}", path.display()));
+                out.warning(format!("{} was already deleted", path.display()));
+            }
+            UndoAction::LinesRemoved(insertion, line) => {
+                out.text(format!(
+                    "removed    {}:{} ({} line(s))",
+                    insertion.path.display(),
+                    line,
+                    insertion.lines.len()
+                ));
+                out.report.modified.push(Modification {
+                    path: insertion.path,
+                    line,
+                    inserted: insertion.replaced,
+                    removed: insertion.lines,
+                });
+            }
+            UndoAction::KeptModified(path) => {
+                let message = format!("kept {}, it was modified since generation", path.display());
+                out.text(format!("warning: {}", message));
+                out.warning(message);
+            }
+            UndoAction::LinesNotFound(insertion) => {
+                let message = format!(
+                    "couldn't find the inserted lines in {}, remove them by hand:",
+                    insertion.path.display()
+                );
+                out.text(format!("warning: {}", message));
+                for line in &insertion.lines {
+                    out.text(format!("    {}", line));
+                }
+                out.warning(format!("{}\n{}", message, insertion.lines.join("\n")));
+            }
+        }
+    }
+
+    out.finish(0)
+}
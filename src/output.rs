@@ -0,0 +1,221 @@
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::generation::render::{Insertion, Removal};
+use crate::generation::Plan;
+
+/// How commands print their results
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Default)]
+pub enum OutputFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+    /// A single JSON report, described in the README
+    Json,
+}
+
+/// The result of a command, printed as JSON with `--output json`. Fields are only ever added to
+/// it, so scripts can rely on the ones documented in the README.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Report {
+    /// The subcommand, e.g. `gen-feat`
+    pub command: String,
+    /// Whether the command exited with status 0
+    pub success: bool,
+    pub created: Vec<PathBuf>,
+    pub modified: Vec<Modification>,
+    pub deleted: Vec<PathBuf>,
+    pub warnings: Vec<String>,
+    pub errors: Vec<ReportError>,
+    /// Command specific result, e.g. the modules for `list`, `null` for most commands
+    pub data: Option<serde_json::Value>,
+}
+
+/// Lines inserted into, or removed from, an existing file
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Modification {
+    pub path: PathBuf,
+    /// 1-based line of the first inserted or removed line
+    pub line: usize,
+    pub inserted: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ReportError {
+    /// Stable snake_case identifier, e.g. `name_collision`
+    pub code: String,
+    pub message: String,
+}
+
+impl From<Insertion> for Modification {
+    fn from(insertion: Insertion) -> Self {
+        Modification {
+            path: insertion.path,
+            line: insertion.line,
+            inserted: insertion.lines,
+            removed: insertion.replaced,
+        }
+    }
+}
+
+impl From<Removal> for Modification {
+    fn from(removal: Removal) -> Self {
+        Modification {
+            path: removal.path,
+            line: removal.line,
+            inserted: removal.remaining,
+            removed: removal.lines,
+        }
+    }
+}
+
+impl Report {
+    pub fn new(command: &str) -> Self {
+        Report {
+            command: command.to_string(),
+            success: true,
+            created: Vec::new(),
+            modified: Vec::new(),
+            deleted: Vec::new(),
+            warnings: Vec::new(),
+            errors: Vec::new(),
+            data: None,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Reports should always be serializable")
+    }
+}
+
+/// Collects the report of a command, and prints the text lines only in text mode
+pub struct Output {
+    format: OutputFormat,
+    pub report: Report,
+}
+
+impl Output {
+    pub fn new(format: OutputFormat, command: &str) -> Self {
+        Output {
+            format,
+            report: Report::new(command),
+        }
+    }
+
+    pub fn is_json(&self) -> bool {
+        self.format == OutputFormat::Json
+    }
+
+    /// Prints `line` to stdout in text mode
+    pub fn text(&self, line: impl Display) {
+        if !self.is_json() {
+            println!("{}", line);
+        }
+    }
+
+    pub fn warning(&mut self, message: impl Display) {
+        self.report.warnings.push(message.to_string());
+    }
+
+    pub fn error(&mut self, code: &str, message: impl Display) {
+        self.report.errors.push(ReportError {
+            code: code.to_string(),
+            message: message.to_string(),
+        });
+    }
+
+    /// Records the files a generation created, the lines it inserted and what it couldn't do
+    pub fn plan(&mut self, plan: Plan) {
+        self.report.created.extend(plan.created);
+        self.report
+            .modified
+            .extend(plan.insertions.into_iter().map(Modification::from));
+        for warning in plan.warnings {
+            if !self.is_json() {
+                eprintln!("warning: {}", warning);
+            }
+            self.warning(warning);
+        }
+    }
+
+    pub fn data(&mut self, data: &impl Serialize) {
+        self.report.data =
+            Some(serde_json::to_value(data).expect("Report data should always be serializable"));
+    }
+
+    /// Prints the report in JSON mode, and passes the exit code through
+    pub fn finish(mut self, exit_code: i32) -> i32 {
+        if self.is_json() {
+            self.report.success = exit_code == 0;
+            println!("{}", self.report.to_json());
+        }
+        exit_code
+    }
+}
+
+/// Reports panics as an `internal_error`, so JSON output stays parseable
+pub fn install_panic_hook(command: &str) {
+    let command = command.to_string();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = info
+            .payload()
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| info.payload().downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_default();
+        let mut report = Report::new(&command);
+        report.success = false;
+        report.errors.push(ReportError {
+            code: "internal_error".to_string(),
+            message,
+        });
+        // Printing again would panic inside the hook when stdout is what failed
+        let _ = writeln!(io::stdout(), "{}", report.to_json());
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_schema() {
+        let mut output = Output::new(OutputFormat::Json, "gen-feat");
+        output.plan(Plan {
+            created: vec![PathBuf::from("feature/home/api/build.gradle.kts")],
+            insertions: vec![Insertion {
+                path: PathBuf::from("settings.gradle.kts"),
+                line: 3,
+                lines: vec!["include(\":feature:home:api\")".into()],
+                replaced: Vec::new(),
+            }],
+            warnings: Vec::new(),
+        });
+        output.warning("careful");
+        output.error("name_collision", "taken");
+        output.report.success = false;
+
+        assert_eq!(
+            serde_json::to_value(&output.report).unwrap(),
+            serde_json::json!({
+                "command": "gen-feat",
+                "success": false,
+                "created": ["feature/home/api/build.gradle.kts"],
+                "modified": [{
+                    "path": "settings.gradle.kts",
+                    "line": 3,
+                    "inserted": ["include(\":feature:home:api\")"],
+                    "removed": [],
+                }],
+                "deleted": [],
+                "warnings": ["careful"],
+                "errors": [{ "code": "name_collision", "message": "taken" }],
+                "data": null,
+            })
+        );
+    }
+}
@@ -0,0 +1,460 @@
+use std::io::{self, BufRead, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use similar::TextDiff;
+
+use crate::args_parser::{Cli, Command};
+use crate::generation::file_system::FileSystem;
+use crate::generation::gen_context::{CtxCreationError, GenCtx};
+use crate::generation::listing::Listing;
+use crate::generation::templates::{self, TemplateSet};
+use crate::generation::Generator;
+use crate::output::{Modification, Output, OutputFormat};
+use crate::wizard::NewKind;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+/// The generation context couldn't be created, `data.code` tells why
+const GENERATION_ERROR: i64 = -32000;
+
+#[derive(Deserialize)]
+struct Request {
+    /// Missing for notifications, which get no response
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct RpcError {
+    code: i64,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl ToString) -> Self {
+        RpcError {
+            code,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+}
+
+impl From<CtxCreationError> for RpcError {
+    fn from(err: CtxCreationError) -> Self {
+        RpcError {
+            code: GENERATION_ERROR,
+            message: err.to_string(),
+            data: Some(json!({ "code": err.code() })),
+        }
+    }
+}
+
+/// Parameters of `planGeneration`, `generate` and `validateName`, mirroring the `gen-*` arguments
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationParams {
+    kind: NewKind,
+    name: String,
+    /// The existing feature a subfeature is added to
+    feature: Option<String>,
+    #[serde(default)]
+    apps: Vec<String>,
+    #[serde(default)]
+    uses_libs: Vec<String>,
+    #[serde(default)]
+    navigates_to: Vec<String>,
+}
+
+impl GenerationParams {
+    fn command(self) -> Command {
+        match self.kind {
+            NewKind::Feature => Command::GenFeat {
+                feature: self.name,
+                apps: self.apps,
+                uses_libs: self.uses_libs,
+                navigates_to: self.navigates_to,
+            },
+            NewKind::Subfeature => Command::GenSubfeat {
+                feature: self.feature.unwrap_or_default(),
+                screen: self.name,
+            },
+            NewKind::Lib => Command::GenLib {
+                lib: self.name,
+                apps: self.apps,
+            },
+        }
+    }
+}
+
+/// Answers JSON-RPC 2.0 requests, keeping the config and the templates loaded between them
+pub struct Server {
+    cli: Cli,
+    /// Read at startup, both listed and rendered
+    templates: TemplateSet,
+    /// The project the requests are about
+    fs: Arc<dyn FileSystem>,
+}
+
+impl Server {
+    pub fn new(cli: Cli) -> Self {
+        let fs = Arc::new(cli.file_system());
+        Self::new_in(cli, fs)
+    }
+
+    /// Like `new`, for the project in `fs`
+    pub fn new_in(cli: Cli, fs: Arc<dyn FileSystem>) -> Self {
+        Server {
+            cli,
+            templates: TemplateSet::load(fs.as_ref()),
+            fs,
+        }
+    }
+
+    /// Answers every request read from `input`, one JSON object per line, until it's closed
+    pub fn serve(&self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle(&line) {
+                let response = serde_json::to_string(&response)
+                    .expect("Responses should always be serializable");
+                writeln!(output, "{}", response)?;
+                output.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The response to a single request, `None` for notifications
+    pub fn handle(&self, line: &str) -> Option<Response> {
+        let request = match serde_json::from_str::<Value>(line) {
+            Ok(request) => request,
+            Err(err) => return Some(respond(Value::Null, Err(RpcError::new(PARSE_ERROR, err)))),
+        };
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let request = match serde_json::from_value::<Request>(request) {
+            Ok(request) => request,
+            Err(err) => return Some(respond(id, Err(RpcError::new(INVALID_REQUEST, err)))),
+        };
+
+        // A broken template panics while rendering, which shouldn't take the server down
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            self.call(&request.method, request.params)
+        }))
+        .unwrap_or_else(|panic| {
+            let message = panic
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_default();
+            Err(RpcError::new(INTERNAL_ERROR, message))
+        });
+        request.id.map(|id| respond(id, result))
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "listFeatures" => Ok(json!(Listing::load(self.fs.as_ref()))),
+            "listTemplates" => Ok(self.list_templates()),
+            "validateName" => {
+                let params = parse_params::<GenerationParams>(params)?;
+                Ok(
+                    match GenCtx::from_cli_in(self.cli_for(params.command()), self.fs.as_ref()) {
+                        Ok(_) => json!({ "valid": true }),
+                        Err(err) => json!({
+                            "valid": false,
+                            "error": { "code": err.code(), "message": err.to_string() },
+                        }),
+                    },
+                )
+            }
+            "planGeneration" => {
+                let params = parse_params::<GenerationParams>(params)?;
+                let preview = self.generator(params.command())?.preview();
+                let created: Vec<_> = preview
+                    .created
+                    .iter()
+                    .map(|(path, content)| json!({ "path": path, "content": content }))
+                    .collect();
+                let modified: Vec<_> = preview
+                    .edited
+                    .iter()
+                    .map(|(path, content)| {
+                        let original = self.fs.read_to_string(path).unwrap_or_default();
+                        let name = path.display().to_string();
+                        let insertions: Vec<_> = preview
+                            .plan
+                            .insertions
+                            .iter()
+                            .filter(|insertion| &insertion.path == path)
+                            .cloned()
+                            .map(Modification::from)
+                            .collect();
+                        json!({
+                            "path": path,
+                            "insertions": insertions,
+                            "diff": TextDiff::from_lines(&original, content)
+                                .unified_diff()
+                                .header(&name, &name)
+                                .to_string(),
+                        })
+                    })
+                    .collect();
+                Ok(json!({
+                    "created": created,
+                    "modified": modified,
+                    "warnings": preview.plan.warnings,
+                }))
+            }
+            "generate" => {
+                let params = parse_params::<GenerationParams>(params)?;
+                let command = params.command();
+                let mut out = Output::new(OutputFormat::Json, command.name());
+                out.plan(self.generator(command)?.generate());
+                Ok(json!(out.report))
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method `{}`", method),
+            )),
+        }
+    }
+
+    fn cli_for(&self, command: Command) -> Cli {
+        Cli {
+            command,
+            ..self.cli.clone()
+        }
+    }
+
+    fn generator(&self, command: Command) -> Result<Generator<'static>, RpcError> {
+        Ok(Generator::from_cli_in(
+            self.cli_for(command),
+            &self.templates,
+            self.fs.clone(),
+        )?)
+    }
+
+    fn list_templates(&self) -> Value {
+        let templates = self
+            .templates
+            .templates
+            .iter()
+            .cloned()
+            .chain(self.templates.partials.iter().cloned().map(|mut partial| {
+                partial.name = format!("{}/{}", templates::PARTIALS_DIR, partial.name);
+                partial
+            }))
+            .map(|template| {
+                json!({
+                    "name": template.name,
+                    "overridden": template.path.is_some(),
+                    "path": template.path,
+                })
+            })
+            .collect();
+        Value::Array(templates)
+    }
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|err| RpcError::new(INVALID_PARAMS, err))
+}
+
+fn respond(id: Value, result: Result<Value, RpcError>) -> Response {
+    let (result, error) = match result {
+        Ok(result) => (Some(result), None),
+        Err(error) => (None, Some(error)),
+    };
+    Response {
+        jsonrpc: "2.0",
+        id,
+        result,
+        error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::file_system::MemoryFileSystem;
+    use std::path::Path;
+
+    /// A Kotlin project in memory, like the one of the integration tests
+    fn project() -> Arc<MemoryFileSystem> {
+        Arc::new(
+            MemoryFileSystem::new()
+                .with_file(
+                    "app/src/main/java/test/base/package/testapp/Application.kt",
+                    include_str!("../tests/mock/Application.kt"),
+                )
+                .with_file(
+                    "settings.gradle.kts",
+                    include_str!("../tests/mock/settings.gradle.kts"),
+                )
+                .with_file(
+                    "buildSrc/src/main/kotlin/app-modules.kt",
+                    include_str!("../tests/mock/app-modules.kts"),
+                )
+                .with_file(
+                    "app/build.gradle.kts",
+                    include_str!("../tests/mock/app-build-gradle.kts"),
+                ),
+        )
+    }
+
+    fn server() -> Server {
+        server_in(project())
+    }
+
+    fn server_in(fs: Arc<MemoryFileSystem>) -> Server {
+        Server::new_in(
+            Cli {
+                debug: false,
+                base_package: Some("test.base.package".into()),
+                app_name: Some("test-app".into()),
+                output: OutputFormat::Text,
+                project_dir: None,
+                app_targets: Vec::new(),
+                command: Command::Undo,
+            },
+            fs,
+        )
+    }
+
+    fn result(response: Option<Response>) -> Value {
+        response.unwrap().result.unwrap()
+    }
+
+    fn error_code(response: Option<Response>) -> i64 {
+        response.unwrap().error.unwrap().code
+    }
+
+    #[test]
+    fn protocol_errors() {
+        let server = server();
+
+        assert_eq!(PARSE_ERROR, error_code(server.handle("{")));
+        assert_eq!(INVALID_REQUEST, error_code(server.handle("{\"id\": 1}")));
+        assert_eq!(json!(1), server.handle("{\"id\": 1}").unwrap().id);
+        assert_eq!(
+            METHOD_NOT_FOUND,
+            error_code(server.handle("{\"jsonrpc\": \"2.0\", \"id\": 1, \"method\": \"nope\"}"))
+        );
+        assert_eq!(
+            INVALID_PARAMS,
+            error_code(server.handle(
+                "{\"jsonrpc\": \"2.0\", \"id\": 1, \"method\": \"validateName\", \"params\": {}}"
+            ))
+        );
+        assert_eq!(
+            None,
+            server.handle("{\"jsonrpc\": \"2.0\", \"method\": \"listFeatures\"}")
+        );
+    }
+
+    #[test]
+    fn validate_name() {
+        let server = server();
+        let validate = |name: &str| {
+            result(server.handle(&format!(
+                "{{\"jsonrpc\": \"2.0\", \"id\": 7, \"method\": \"validateName\", \"params\": {{\"kind\": \"lib\", \"name\": \"{}\"}}}}",
+                name
+            )))
+        };
+
+        assert_eq!(json!({ "valid": true }), validate("saved-faces"));
+        assert_eq!("keyword_name", validate("class")["error"]["code"]);
+    }
+
+    #[test]
+    fn list_templates() {
+        let fs = project();
+        let server = server_in(fs.clone());
+        // Written after startup, so neither listed nor rendered
+        fs.write(
+            Path::new(".feature_generator/templates/impl/ImplBuild.handlebars"),
+            "custom",
+        )
+        .unwrap();
+
+        let templates = result(
+            server.handle("{\"jsonrpc\": \"2.0\", \"id\": \"a\", \"method\": \"listTemplates\"}"),
+        );
+
+        let impl_build = templates
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|template| template["name"] == "impl/ImplBuild")
+            .unwrap();
+        assert_eq!(false, impl_build["overridden"]);
+    }
+
+    #[test]
+    fn plan_generation() {
+        let fs = project();
+        let before = fs.snapshot();
+        let plan = result(server_in(fs.clone()).handle(
+            "{\"jsonrpc\": \"2.0\", \"id\": 1, \"method\": \"planGeneration\", \"params\": {\"kind\": \"lib\", \"name\": \"saved-faces\"}}",
+        ));
+
+        assert_eq!(before, fs.snapshot());
+        assert!(plan["created"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|file| file["path"] == "library/saved-faces/api/build.gradle.kts"));
+        let settings = plan["modified"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|file| file["path"] == "settings.gradle.kts")
+            .unwrap();
+        let diff = settings["diff"].as_str().unwrap();
+        assert!(
+            diff.contains("+include(\":library:saved-faces:api\")"),
+            "{}",
+            diff
+        );
+    }
+
+    #[test]
+    fn generate() {
+        let fs = project();
+        let server = server_in(fs.clone());
+        let request = "{\"jsonrpc\": \"2.0\", \"id\": 2, \"method\": \"generate\", \"params\": {\"kind\": \"lib\", \"name\": \"saved-faces\"}}";
+
+        let report = result(server.handle(request));
+
+        assert_eq!(true, report["success"]);
+        assert!(fs.exists(Path::new("library/saved-faces/api/build.gradle.kts")));
+        assert_eq!(
+            "name_collision",
+            server.handle(request).unwrap().error.unwrap().data.unwrap()["code"]
+        );
+    }
+}
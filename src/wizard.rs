@@ -0,0 +1,288 @@
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, IsTerminal};
+
+use clap::ValueEnum;
+use dialoguer::{Confirm, FuzzySelect, Input, Select};
+use serde::Deserialize;
+
+use crate::args_parser::{Cli, Command};
+use crate::generation::gen_context::{existing_features, GenCtx};
+use crate::generation::{Generator, Plan};
+use crate::output::Output;
+
+/// What the `new` command generates
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum NewKind {
+    Feature,
+    Subfeature,
+    Lib,
+}
+
+impl NewKind {
+    const ALL: [NewKind; 3] = [NewKind::Feature, NewKind::Subfeature, NewKind::Lib];
+}
+
+impl Display for NewKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            NewKind::Feature => write!(f, "feature"),
+            NewKind::Subfeature => write!(f, "subfeature"),
+            NewKind::Lib => write!(f, "library"),
+        }
+    }
+}
+
+/// Everything `new` needs to know, either asked for or given as arguments
+#[derive(Debug, PartialEq)]
+pub struct Answers {
+    pub kind: NewKind,
+    pub name: String,
+    /// The feature a subfeature is added to
+    pub feature: Option<String>,
+}
+
+impl Answers {
+    /// The generation command these answers stand for
+    pub fn command(self) -> Command {
+        match self.kind {
+            NewKind::Feature => Command::GenFeat {
+                feature: self.name,
+                apps: Vec::new(),
+                uses_libs: Vec::new(),
+                navigates_to: Vec::new(),
+            },
+            NewKind::Subfeature => Command::GenSubfeat {
+                feature: self.feature.unwrap_or_default(),
+                screen: self.name,
+            },
+            NewKind::Lib => Command::GenLib {
+                lib: self.name,
+                apps: Vec::new(),
+            },
+        }
+    }
+}
+
+/// Runs the `new` command, asking for the missing arguments when stdin is a terminal and the
+/// output isn't JSON
+pub fn run(
+    cli: &Cli,
+    kind: Option<NewKind>,
+    name: Option<String>,
+    feature: Option<String>,
+    yes: bool,
+    mut out: Output,
+) -> i32 {
+    let interactive = io::stdin().is_terminal() && !out.is_json();
+    let answers = if interactive {
+        ask(cli, kind, name, feature)
+    } else {
+        from_arguments(kind, name, feature)
+    };
+    let answers = match answers {
+        Ok(answers) => answers,
+        Err(message) => {
+            out.text(&message);
+            out.error("missing_argument", message);
+            return out.finish(1);
+        }
+    };
+
+    let cli = with_command(cli, answers.command());
+    let plan = match Generator::from_cli(cli.clone()) {
+        Ok(generator) => generator.plan(),
+        Err(err) => {
+            out.text(&err);
+            out.error(err.code(), err);
+            return out.finish(1);
+        }
+    };
+    out.text(render_plan(&plan).trim_end());
+
+    if interactive && !yes {
+        let confirmed = Confirm::new()
+            .with_prompt("Generate these files?")
+            .default(true)
+            .interact()
+            .unwrap_or(false);
+        if !confirmed {
+            out.text("Nothing was generated");
+            return out.finish(0);
+        }
+    }
+
+    out.plan(
+        Generator::from_cli(cli)
+            .expect("Generation context was already created for the plan")
+            .generate(),
+    );
+    out.finish(0)
+}
+
+/// The answers given as arguments, when there's no one to ask for the rest
+pub fn from_arguments(
+    kind: Option<NewKind>,
+    name: Option<String>,
+    feature: Option<String>,
+) -> Result<Answers, String> {
+    let kind = kind.ok_or("`--kind` is required when stdin isn't a terminal")?;
+    let name = name.ok_or("The name is required when stdin isn't a terminal")?;
+    if kind == NewKind::Subfeature && feature.is_none() {
+        return Err("`--feature` is required for subfeatures when stdin isn't a terminal".into());
+    }
+    Ok(Answers {
+        kind,
+        name,
+        feature,
+    })
+}
+
+fn ask(
+    cli: &Cli,
+    kind: Option<NewKind>,
+    name: Option<String>,
+    feature: Option<String>,
+) -> Result<Answers, String> {
+    let kind = match kind {
+        Some(kind) => kind,
+        None => {
+            NewKind::ALL[Select::new()
+                .with_prompt("What do you want to generate?")
+                .items(&NewKind::ALL)
+                .default(0)
+                .interact()
+                .map_err(|err| err.to_string())?]
+        }
+    };
+
+    let feature = match (kind, feature) {
+        (NewKind::Subfeature, None) => {
+            let features = existing_features(&cli.file_system());
+            if features.is_empty() {
+                return Err(
+                    "No feature found in the `feature` directory, generate one first".into(),
+                );
+            }
+            let index = FuzzySelect::new()
+                .with_prompt("Which feature is the subfeature added to?")
+                .items(&features)
+                .interact()
+                .map_err(|err| err.to_string())?;
+            Some(features[index].clone())
+        }
+        (_, feature) => feature,
+    };
+
+    let name = match name {
+        Some(name) => name,
+        None => Input::<String>::new()
+            .with_prompt(format!("Name of the new {}", kind))
+            .validate_with(|input: &String| {
+                let answers = Answers {
+                    kind,
+                    name: input.clone(),
+                    feature: feature.clone(),
+                };
+                GenCtx::from_cli(with_command(cli, answers.command()))
+                    .map(|_| ())
+                    .map_err(|err| err.to_string())
+            })
+            .interact_text()
+            .map_err(|err| err.to_string())?,
+    };
+
+    Ok(Answers {
+        kind,
+        name,
+        feature,
+    })
+}
+
+fn with_command(cli: &Cli, command: Command) -> Cli {
+    Cli {
+        command,
+        ..cli.clone()
+    }
+}
+
+/// One line for each file the plan creates and each existing file it inserts lines into
+pub fn render_plan(plan: &Plan) -> String {
+    let mut rendered = String::new();
+    for path in &plan.created {
+        rendered.push_str(&format!("create     {}\n", path.display()));
+    }
+    for insertion in &plan.insertions {
+        rendered.push_str(&format!(
+            "insert     {}:{} ({} line(s))\n",
+            insertion.path.display(),
+            insertion.line,
+            insertion.lines.len()
+        ));
+    }
+    for warning in &plan.warnings {
+        rendered.push_str(&format!("warning    {}\n", warning));
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::render::Insertion;
+    use std::path::PathBuf;
+
+    #[test]
+    fn answers_from_arguments() {
+        assert_eq!(
+            from_arguments(
+                Some(NewKind::Subfeature),
+                Some("details".into()),
+                Some("home".into())
+            ),
+            Ok(Answers {
+                kind: NewKind::Subfeature,
+                name: "details".into(),
+                feature: Some("home".into()),
+            })
+        );
+        assert!(from_arguments(None, Some("home".into()), None).is_err());
+        assert!(from_arguments(Some(NewKind::Feature), None, None).is_err());
+        assert!(from_arguments(Some(NewKind::Subfeature), Some("details".into()), None).is_err());
+    }
+
+    #[test]
+    fn subfeature_answers_become_gen_subfeat() {
+        let command = Answers {
+            kind: NewKind::Subfeature,
+            name: "details".into(),
+            feature: Some("home".into()),
+        }
+        .command();
+
+        assert!(matches!(
+            command,
+            Command::GenSubfeat { feature, screen } if feature == "home" && screen == "details"
+        ));
+    }
+
+    #[test]
+    fn render_plan_lists_files_and_insertions() {
+        let plan = Plan {
+            created: vec![PathBuf::from("feature/home/api/build.gradle.kts")],
+            insertions: vec![Insertion {
+                path: PathBuf::from("settings.gradle.kts"),
+                line: 12,
+                lines: vec!["a".into(), "b".into()],
+                replaced: Vec::new(),
+            }],
+            warnings: Vec::new(),
+        };
+
+        assert_eq!(
+            render_plan(&plan),
+            "create     feature/home/api/build.gradle.kts\n\
+            insert     settings.gradle.kts:12 (2 line(s))\n"
+        );
+    }
+}
@@ -2,6 +2,7 @@ import org.gradle.kotlin.dsl.DependencyHandlerScope
 
 // ===== feature modules =====
 val DependencyHandlerScope.camera get() = createProject(":feature:camera")
+val DependencyHandlerScope.home get() = createProject(":feature:home")
 val DependencyHandlerScope.memeFaceFusion get() = createProject(":feature:meme-face-fusion")
 val DependencyHandlerScope.memeSearch get() = createProject(":feature:meme-search")
 val DependencyHandlerScope.memeGenerator get() = createProject(":feature:meme-generator")
@@ -13,4 +14,3 @@ val DependencyHandlerScope.appState get() = createProject(":library:app-state")
 
 val DependencyHandlerScope.memeGeneration get() = createProject(":library:meme-generation")
 val DependencyHandlerScope.savedFaces get() = createProject(":library:saved-faces")
-val DependencyHandlerScope.home get() = createProject(":feature:home")
@@ -1,9 +1,9 @@
 package com.cardinalblue.fresh
 
 import android.app.Application
-import test.base.package.facedetection.impl.FaceDetection
 import androidx.lifecycle.ViewModel
 import com.cardinalblue.AppEventTracker
+import test.base.package.facedetection.impl.FaceDetection
 import test.base.package.start.impl.entry.StartFeatureRoot
 import com.cardinalblue.platform.EventTracker
 import com.cardinalblue.platform.Platform
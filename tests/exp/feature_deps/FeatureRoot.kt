@@ -0,0 +1,22 @@
+package test.base.package.home.impl.root
+
+import test.base.package.home.api.HomeFeatureEntry
+import test.base.package.home.impl.subfeature.home.HomeSubfeature
+import test.base.package.testapp.savedfaces.SavedFacesProvider
+import com.cardinalblue.platform.PlatformProvider
+import com.cardinalblue.skeleton.processor.FeatureRoot
+import dagger.Module
+
+@Module
+interface HomeRootModule
+
+@FeatureRoot(
+    dependencies = [
+        PlatformProvider::class,
+        SavedFacesProvider::class,
+    ],
+    rootModule = HomeRootModule::class,
+    startSubfeature = HomeSubfeature::class,
+    featureEntry = HomeFeatureEntry::class,
+)
+interface HomeFeatureRoot
@@ -11,6 +11,6 @@ val DependencyHandlerScope.landing get() = createProject(":feature:landing")
 
 val DependencyHandlerScope.appState get() = createProject(":library:app-state")
 
+val DependencyHandlerScope.faceDetection get() = createProject(":library:face-detection")
 val DependencyHandlerScope.memeGeneration get() = createProject(":library:meme-generation")
 val DependencyHandlerScope.savedFaces get() = createProject(":library:saved-faces")
-val DependencyHandlerScope.faceDetection get() = createProject(":library:face-detection")
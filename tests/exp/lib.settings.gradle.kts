@@ -31,6 +31,6 @@ include(":core:scaffold:processor")
 include(":core:data")
 include(":feature:start:api")
 include(":feature:start:impl")
-include(":theme")
 include(":library:face-detection:api")
 include(":library:face-detection:impl")
+include(":theme")
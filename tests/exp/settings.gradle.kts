@@ -29,8 +29,8 @@ include(":core:platform")
 include(":core:scaffold")
 include(":core:scaffold:processor")
 include(":core:data")
+include(":feature:home:api")
+include(":feature:home:impl")
 include(":feature:start:api")
 include(":feature:start:impl")
 include(":theme")
-include(":feature:home:api")
-include(":feature:home:impl")
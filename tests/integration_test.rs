@@ -1,89 +1,105 @@
-use std::{fs::File, path::Path};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use convert_case::{Case, Casing};
 use feature_generator::generation::{
-    gen_context::{AppCtx, Config, GenCtx, Library},
-    Feature, Generator, Subfeature,
+    dependency::{self, Dependency, DependencyError},
+    file_system::{FileSystem, MemoryFileSystem},
+    gen_context::{AppCtx, Config, CtxCreationError, GenCtx, Library},
+    history::History,
+    manifest::Manifest,
+    Feature, Generator, Plan, Subfeature,
 };
-use serial_test::serial;
-
-fn setup_application(app_name: &str) {
-    let path = Path::new("app/src/main/java/test/base/package").join(app_name.to_case(Case::Flat));
-    std::fs::create_dir_all(&path).unwrap();
-    std::fs::write(
-        path.join("Application.kt"),
-        include_str!("./mock/Application.kt"),
-    )
-    .unwrap()
-}
-
-fn setup_settings() {
-    let path = Path::new("settings.gradle.kts");
-    std::fs::write(path, include_str!("./mock/settings.gradle.kts")).unwrap();
-}
-
-fn setup_app_modules() {
-    let path = Path::new("buildSrc/src/main/kotlin/app-modules.kt");
-    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
-    std::fs::write(path, include_str!("./mock/app-modules.kts")).unwrap();
-}
 
-fn setup_app_build_gradle() {
-    let path = Path::new("app/build.gradle.kts");
-    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
-    std::fs::write(path, include_str!("./mock/app-build-gradle.kts")).unwrap();
+fn application_path(app_name: &str) -> PathBuf {
+    Path::new("app/src/main/java/test/base/package")
+        .join(app_name.to_case(Case::Flat))
+        .join("Application.kt")
 }
 
-fn teardown_application() {
-    std::fs::remove_dir_all(Path::new("app")).unwrap();
+/// A project with Kotlin build scripts and the accessors of buildSrc
+fn kotlin_project(app_name: &str) -> Arc<MemoryFileSystem> {
+    Arc::new(
+        MemoryFileSystem::new()
+            .with_file(
+                application_path(app_name),
+                include_str!("./mock/Application.kt"),
+            )
+            .with_file(
+                "settings.gradle.kts",
+                include_str!("./mock/settings.gradle.kts"),
+            )
+            .with_file(
+                "buildSrc/src/main/kotlin/app-modules.kt",
+                include_str!("./mock/app-modules.kts"),
+            )
+            .with_file(
+                "app/build.gradle.kts",
+                include_str!("./mock/app-build-gradle.kts"),
+            ),
+    )
 }
 
-fn teardown_settings() {
-    std::fs::remove_file(Path::new("settings.gradle.kts")).unwrap();
+fn groovy_project(app_name: &str) -> Arc<MemoryFileSystem> {
+    Arc::new(
+        MemoryFileSystem::new()
+            .with_file(
+                application_path(app_name),
+                include_str!("./mock/Application.kt"),
+            )
+            .with_file("settings.gradle", include_str!("./mock/settings.gradle"))
+            .with_file("app/build.gradle", include_str!("./mock/app-build.gradle")),
+    )
 }
 
-fn teardown_app_modules() {
-    let path = Path::new("buildSrc");
-    std::fs::remove_dir_all(path).unwrap();
+fn generate(ctx: GenCtx, fs: &Arc<MemoryFileSystem>) -> Plan {
+    Generator::for_context(ctx, fs.clone())
+        .expect("Test projects have an application class")
+        .generate()
 }
 
 #[test]
-#[serial]
 fn test_generate_feature() {
-    setup_application("test-app");
-    setup_settings();
-    setup_app_modules();
-    setup_app_build_gradle();
+    let fs = kotlin_project("test-app");
 
     let feature = Feature {
         app_context: default_app_ctx(),
         feature_name: "home".into(),
+        apps: Vec::new(),
+        used_libraries: Vec::new(),
+        navigation_targets: Vec::new(),
     };
     let feature = GenCtx::from(feature);
-    let generator = Generator::new(feature);
-    generator.generate();
+    generate(feature, &fs);
 
     assert_content_eq(
+        &fs,
         Path::new("feature/home/api/src/main/kotlin/test/base/package/HomeFeatureEntry.kt"),
         include_str!("./exp/feature_api/HomeFeatureEntry.kt"),
     );
 
     assert_content_eq(
+        &fs,
         Path::new("feature/home/api/build.gradle.kts"),
         include_str!("./exp/feature_api/build.gradle.kts"),
     );
 
     assert_content_eq(
+        &fs,
         Path::new("feature/home/impl/build.gradle.kts"),
         include_str!("./exp/feature_impl/build.gradle.kts"),
     );
 
     assert_content_eq(
+        &fs,
         Path::new("feature/home/impl/src/main/kotlin/test/base/package/root/HomeFeatureRoot.kt"),
         include_str!("./exp/feature_impl/FeatureRoot.kt"),
     );
 
     assert_content_eq(
+        &fs,
         Path::new(
             "feature/home/impl/src/main/kotlin/test/base/package/subfeature/home/HomeSubfeature.kt",
         ),
@@ -91,6 +107,7 @@ fn test_generate_feature() {
     );
 
     assert_content_eq(
+        &fs,
         Path::new(
             "feature/home/impl/src/main/kotlin/test/base/package/subfeature/home/screen/HomeScreen.kt",
         ),
@@ -98,6 +115,7 @@ fn test_generate_feature() {
     );
 
     assert_content_eq(
+        &fs,
         Path::new(
             "feature/home/impl/src/main/kotlin/test/base/package/subfeature/home/screen/HomeScreenViewModel.kt",
         ),
@@ -105,66 +123,144 @@ fn test_generate_feature() {
     );
 
     assert_content_eq(
+        &fs,
         Path::new("app/src/main/java/test/base/package/testapp/Application.kt"),
         include_str!("./exp/app/Application.kt"),
     );
 
     assert_content_eq(
+        &fs,
         Path::new("settings.gradle.kts"),
         include_str!("./exp/settings.gradle.kts"),
     );
 
     assert_content_eq(
+        &fs,
         Path::new("buildSrc/src/main/kotlin/app-modules.kt"),
         include_str!("./exp/app-modules.kts"),
     );
 
     assert_content_eq(
+        &fs,
         Path::new("app/build.gradle.kts"),
         include_str!("./exp/app-build-gradle.kts"),
     );
 
-    std::fs::remove_dir_all("feature").unwrap();
-    teardown_settings();
-    teardown_application();
-    teardown_app_modules();
+    let manifest = Manifest::load_from(fs.as_ref(), Path::new(".feature_generator/manifest.json"));
+    let templates: Vec<&str> = manifest
+        .files
+        .iter()
+        .map(|file| file.template.as_str())
+        .collect();
+    assert_eq!(
+        vec![
+            "api/FeatureEntry",
+            "api/ApiBuild",
+            "impl/ImplBuild",
+            "impl/root/FeatureRoot",
+            "impl/firstpage/Subfeature",
+            "impl/firstpage/screen/FirstPageScreen",
+            "impl/firstpage/screen/FirstPageViewModel",
+        ],
+        templates
+    );
+    assert_eq!(
+        Path::new("feature/home/impl/build.gradle.kts"),
+        manifest.files[2].path
+    );
+    assert_eq!(
+        include_str!("./exp/feature_impl/build.gradle.kts"),
+        manifest.files[2].content
+    );
+
+    let history = History::load_from(fs.as_ref(), Path::new(".feature_generator/history.json"));
+    assert_eq!(1, history.entries.len());
+    let entry = &history.entries[0];
+    assert_eq!("gen-feat home", entry.command);
+    assert_eq!(7, entry.created.len());
+    assert!(history.is_generated(
+        Path::new("feature/home/impl/build.gradle.kts"),
+        include_str!("./exp/feature_impl/build.gradle.kts"),
+    ));
+    let insertions: Vec<(&str, usize, Vec<&str>)> = entry
+        .insertions
+        .iter()
+        .map(|insertion| {
+            (
+                insertion.path.to_str().unwrap(),
+                insertion.line,
+                insertion.lines.iter().map(String::as_str).collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        vec![
+            (
+                "app/src/main/java/test/base/package/testapp/Application.kt",
+                24,
+                vec!["        HomeFeatureRoot::class,"]
+            ),
+            (
+                "app/src/main/java/test/base/package/testapp/Application.kt",
+                6,
+                vec!["import test.base.package.home.impl.root.HomeFeatureRoot"]
+            ),
+            (
+                "settings.gradle.kts",
+                32,
+                vec![
+                    "include(\":feature:home:api\")",
+                    "include(\":feature:home:impl\")"
+                ]
+            ),
+            (
+                "buildSrc/src/main/kotlin/app-modules.kt",
+                5,
+                vec!["val DependencyHandlerScope.home get() = createProject(\":feature:home\")"]
+            ),
+            (
+                "app/build.gradle.kts",
+                61,
+                vec!["    implementation(*home.all())"]
+            ),
+        ],
+        insertions
+    );
 }
 
 #[test]
-#[serial]
 fn generate_subfeature() {
+    let fs = Arc::new(MemoryFileSystem::new());
     let feature = Subfeature {
         app_context: default_app_ctx(),
         feature_name: "home".into(),
         subfeature_name: "home-details".into(),
     };
     let feature = GenCtx::from(feature);
-    let generator = Generator::new(feature);
-    generator.generate();
+    generate(feature, &fs);
 
     assert_content_eq(
+        &fs,
         Path::new("feature/home/impl/src/main/kotlin/test/base/package/subfeature/homedetails/HomeDetailsSubfeature.kt"),
         include_str!("./exp/feature_impl/subfeature_gen/Subfeature.kt"),
     );
 
     assert_content_eq(
+        &fs,
         Path::new("feature/home/impl/src/main/kotlin/test/base/package/subfeature/homedetails/screen/HomeDetailsScreen.kt"),
         include_str!("./exp/feature_impl/subfeature_gen/Screen.kt"),
     );
 
     assert_content_eq(
+        &fs,
         Path::new("feature/home/impl/src/main/kotlin/test/base/package/subfeature/homedetails/screen/HomeDetailsScreenViewModel.kt"),
         include_str!("./exp/feature_impl/subfeature_gen/ScreenViewModel.kt"),
     );
 }
 
 #[test]
-#[serial]
 fn generate_config() {
-    setup_application("my-app");
-    setup_settings();
-    setup_app_modules();
-    setup_app_build_gradle();
+    let fs = kotlin_project("my-app");
 
     let config = Config {
         app_name: Some("my-app".to_string()),
@@ -173,22 +269,18 @@ fn generate_config() {
     };
 
     let ctx = GenCtx::from(config);
-    let generator = Generator::new(ctx);
-    generator.generate();
+    generate(ctx, &fs);
 
     assert_content_eq(
+        &fs,
         Path::new(".feature_generator_config.toml"),
         include_str!("./exp/feature_generator_config.toml"),
     );
 }
 
 #[test]
-#[serial]
 fn generate_library() {
-    setup_application("my-app");
-    setup_settings();
-    setup_app_modules();
-    setup_app_build_gradle();
+    let fs = kotlin_project("my-app");
 
     let lib = Library {
         app_context: AppCtx {
@@ -197,18 +289,20 @@ fn generate_library() {
             base_package: "test.base.package".to_string(),
         },
         library_name: "face-detection".to_string(),
+        apps: Vec::new(),
     };
 
     let ctx = GenCtx::from(lib);
-    let generator = Generator::new(ctx);
-    generator.generate();
+    generate(ctx, &fs);
 
     assert_content_eq(
+        &fs,
         Path::new("library/face-detection/api/build.gradle.kts"),
         include_str!("./exp/lib/api_build.gradle.kts"),
     );
 
     assert_content_eq(
+        &fs,
         Path::new(
             "library/face-detection/api/src/main/kotlin/test/base/package/myapp/facedetection/FaceDetectionProvider.kt",
         ),
@@ -216,6 +310,7 @@ fn generate_library() {
     );
 
     assert_content_eq(
+        &fs,
         Path::new(
             "library/face-detection/api/src/main/kotlin/test/base/package/myapp/facedetection/GetExample.kt",
         ),
@@ -223,55 +318,348 @@ fn generate_library() {
     );
 
     assert_content_eq(
+        &fs,
         Path::new("library/face-detection/impl/build.gradle.kts"),
         include_str!("./exp/lib/impl_build.gradle.kts"),
     );
 
     assert_content_eq(
+        &fs,
         Path::new("library/face-detection/impl/src/main/kotlin/test/base/package/facedetection/impl/FaceDetection.kt"),
         include_str!("./exp/lib/LibDecl.kt"),
     );
 
     assert_content_eq(
+        &fs,
         Path::new("library/face-detection/impl/src/main/kotlin/test/base/package/facedetection/impl/FaceDetection.kt"),
         include_str!("./exp/lib/LibDecl.kt"),
     );
 
     assert_content_eq(
+        &fs,
         Path::new("library/face-detection/impl/src/main/kotlin/test/base/package/facedetection/impl/usecase/GetExampleUseCase.kt"),
         include_str!("./exp/lib/GetExampleUseCase.kt"),
     );
 
     assert_content_eq(
+        &fs,
         Path::new("app/src/main/java/test/base/package/myapp/Application.kt"),
         include_str!("./exp/app/LibApplication.kt"),
     );
 
     assert_content_eq(
+        &fs,
         Path::new("settings.gradle.kts"),
         include_str!("./exp/lib.settings.gradle.kts"),
     );
 
     assert_content_eq(
+        &fs,
         Path::new("buildSrc/src/main/kotlin/app-modules.kt"),
         include_str!("./exp/lib-app-modules.kts"),
     );
 
     assert_content_eq(
+        &fs,
         Path::new("app/build.gradle.kts"),
         include_str!("./exp/lib-app-build-gradle.kts"),
     );
+}
+
+#[test]
+fn generate_feature_with_groovy_scripts() {
+    let fs = groovy_project("test-app");
+
+    let feature = Feature {
+        app_context: default_app_ctx(),
+        feature_name: "home".into(),
+        apps: Vec::new(),
+        used_libraries: Vec::new(),
+        navigation_targets: Vec::new(),
+    };
+    generate(GenCtx::from(feature), &fs);
+
+    assert_content_eq(
+        &fs,
+        Path::new("feature/home/api/build.gradle"),
+        include_str!("./exp/groovy/api_build.gradle"),
+    );
+
+    assert_content_eq(
+        &fs,
+        Path::new("feature/home/impl/build.gradle"),
+        include_str!("./exp/groovy/impl_build.gradle"),
+    );
+
+    assert_content_eq(
+        &fs,
+        Path::new("settings.gradle"),
+        include_str!("./exp/groovy/settings.gradle"),
+    );
+
+    assert_content_eq(
+        &fs,
+        Path::new("app/build.gradle"),
+        include_str!("./exp/groovy/app-build.gradle"),
+    );
+
+    assert!(!fs.exists(Path::new("feature/home/api/build.gradle.kts")));
+}
+
+#[test]
+fn groovy_project_paths_are_kebab_case() {
+    let fs = groovy_project("test-app");
+    let ctx = GenCtx::feature("memeSearch")
+        .app(default_app_ctx())
+        .build_in(fs.as_ref())
+        .unwrap();
+    generate(ctx, &fs);
+
+    let impl_build = fs
+        .read_to_string(Path::new("feature/meme-search/impl/build.gradle"))
+        .unwrap();
+    assert!(impl_build.contains("implementation project(':feature:meme-search:api')"));
+    let settings = fs.read_to_string(Path::new("settings.gradle")).unwrap();
+    assert!(settings.contains("include ':feature:meme-search:api'"));
+    assert!(settings.contains("include ':feature:meme-search:impl'"));
+}
+
+#[test]
+fn generate_feature_with_dependencies() {
+    let fs = kotlin_project("test-app");
+
+    let feature = Feature {
+        app_context: default_app_ctx(),
+        feature_name: "home".into(),
+        apps: Vec::new(),
+        used_libraries: vec!["saved-faces".into()],
+        navigation_targets: vec!["meme-details".into()],
+    };
+    generate(GenCtx::from(feature), &fs);
+
+    assert_content_eq(
+        &fs,
+        Path::new("feature/home/impl/build.gradle.kts"),
+        include_str!("./exp/feature_deps/build.gradle.kts"),
+    );
 
-    teardown_app_modules();
-    teardown_application();
+    assert_content_eq(
+        &fs,
+        Path::new("feature/home/impl/src/main/kotlin/test/base/package/root/HomeFeatureRoot.kt"),
+        include_str!("./exp/feature_deps/FeatureRoot.kt"),
+    );
+}
+
+#[test]
+fn add_and_remove_dependency() {
+    let fs = kotlin_project("test-app");
+
+    let feature = Feature {
+        app_context: default_app_ctx(),
+        feature_name: "home".into(),
+        apps: Vec::new(),
+        used_libraries: Vec::new(),
+        navigation_targets: Vec::new(),
+    };
+    generate(GenCtx::from(feature), &fs);
+    let build = Path::new("feature/home/impl/build.gradle.kts");
+    let root =
+        Path::new("feature/home/impl/src/main/kotlin/test/base/package/root/HomeFeatureRoot.kt");
+    let generated_build = fs.read_to_string(build).unwrap();
+    let generated_root = fs.read_to_string(root).unwrap();
+    let library = Dependency::Library("saved-faces".into());
+
+    assert_eq!(
+        Err(DependencyError::UnknownLibrary("saved-faces".into())),
+        dependency::add_dependency(fs.as_ref(), &default_app_ctx(), "home", &library)
+    );
+
+    fs.write(Path::new("library/saved-faces/api/build.gradle.kts"), "")
+        .unwrap();
+    let insertions =
+        dependency::add_dependency(fs.as_ref(), &default_app_ctx(), "home", &library).unwrap();
+    assert_eq!(3, insertions.len());
+    let build_content = fs.read_to_string(build).unwrap();
+    assert!(build_content.contains("    implementation(savedFaces.api())\n"));
+    let root_content = fs.read_to_string(root).unwrap();
+    assert!(root_content.contains("        SavedFacesProvider::class,\n"));
+    assert!(
+        root_content.contains("import test.base.package.testapp.savedfaces.SavedFacesProvider\n")
+    );
+
+    assert_eq!(
+        Ok(Vec::new()),
+        dependency::add_dependency(fs.as_ref(), &default_app_ctx(), "home", &library)
+    );
+
+    let removals =
+        dependency::remove_dependency(fs.as_ref(), &default_app_ctx(), "home", &library).unwrap();
+    assert_eq!(3, removals.len());
+    assert_eq!(
+        vec!["    implementation(savedFaces.api())".to_string()],
+        removals[0].lines
+    );
+    assert_content_eq(&fs, build, &generated_build);
+    assert_content_eq(&fs, root, &generated_root);
+}
+
+#[test]
+fn add_dependency_edits_all_or_nothing() {
+    let fs = kotlin_project("test-app");
+    let feature = Feature {
+        app_context: default_app_ctx(),
+        feature_name: "home".into(),
+        apps: Vec::new(),
+        used_libraries: Vec::new(),
+        navigation_targets: Vec::new(),
+    };
+    generate(GenCtx::from(feature), &fs);
+    fs.write(Path::new("library/saved-faces/api/build.gradle.kts"), "")
+        .unwrap();
+    let build = Path::new("feature/home/impl/build.gradle.kts");
+    let root =
+        Path::new("feature/home/impl/src/main/kotlin/test/base/package/root/HomeFeatureRoot.kt");
+    let import = "import test.base.package.testapp.savedfaces.SavedFacesProvider\n";
+    let library = Dependency::Library("saved-faces".into());
+
+    let generated_root = fs.read_to_string(root).unwrap();
+    let imported_root = generated_root.replacen("import ", &format!("{}import ", import), 1);
+    fs.write(root, &imported_root).unwrap();
+    let insertions =
+        dependency::add_dependency(fs.as_ref(), &default_app_ctx(), "home", &library).unwrap();
+    assert_eq!(2, insertions.len());
+    assert_eq!(1, fs.read_to_string(root).unwrap().matches(import).count());
+
+    let fs = kotlin_project("test-app");
+    fs.write(build, "dependencies {\n}\n").unwrap();
+    fs.write(Path::new("library/saved-faces/api/build.gradle.kts"), "")
+        .unwrap();
+    assert_eq!(
+        Err(DependencyError::MissingFile(root.to_path_buf())),
+        dependency::add_dependency(fs.as_ref(), &default_app_ctx(), "home", &library)
+    );
+    assert_content_eq(&fs, build, "dependencies {\n}\n");
+}
+
+#[test]
+fn plan_matches_generation() {
+    let fs = kotlin_project("test-app");
+    let project = fs.snapshot();
+
+    let feature = Feature {
+        app_context: default_app_ctx(),
+        feature_name: "home".into(),
+        apps: Vec::new(),
+        used_libraries: Vec::new(),
+        navigation_targets: Vec::new(),
+    };
+    let preview = Generator::new(GenCtx::from(feature.clone()))
+        .with_file_system(fs.clone())
+        .preview();
+    let plan = preview.plan;
+
+    assert_eq!(project, fs.snapshot());
+
+    let generated = generate(GenCtx::from(feature), &fs);
+    assert_eq!(plan, generated);
+    let entry = History::load_from(fs.as_ref(), Path::new(".feature_generator/history.json"))
+        .entries
+        .pop()
+        .unwrap();
+    let created: Vec<_> = entry.created.into_iter().map(|file| file.path).collect();
+    assert_eq!(plan.created, created);
+    assert_eq!(plan.insertions, entry.insertions);
+    for (path, content) in preview.created.iter().chain(&preview.edited) {
+        assert_content_eq(&fs, path, content);
+    }
+}
+
+#[test]
+fn generate_with_project_templates() {
+    let fs = kotlin_project("test-app");
+    fs.write(
+        Path::new(".feature_generator/templates/partials/file_header.handlebars"),
+        "// License\n",
+    )
+    .unwrap();
+
+    let ctx = GenCtx::feature("home")
+        .app(default_app_ctx())
+        .build_in(fs.as_ref())
+        .unwrap();
+    generate(ctx, &fs);
+
+    let entry = fs
+        .read_to_string(Path::new(
+            "feature/home/api/src/main/kotlin/test/base/package/HomeFeatureEntry.kt",
+        ))
+        .unwrap();
+    assert!(entry.starts_with("// License\n"), "{}", entry);
+}
+
+#[test]
+fn generate_from_builder() {
+    let fs = kotlin_project("test-app");
+    let ctx = GenCtx::library("face-detection")
+        .app(default_app_ctx())
+        .build_in(fs.as_ref())
+        .unwrap();
+
+    assert_eq!(
+        Err(CtxCreationError::ApplicationNotFound("app".into())),
+        Generator::for_context(ctx.clone(), Arc::new(MemoryFileSystem::new())).map(|_| ())
+    );
+
+    let plan = Generator::for_context(ctx, fs.clone()).unwrap().generate();
+    assert_eq!(6, plan.created.len());
+    assert_content_eq(
+        &fs,
+        Path::new("library/face-detection/impl/build.gradle.kts"),
+        include_str!("./exp/lib/impl_build.gradle.kts"),
+    );
+    assert_eq!(
+        Err(CtxCreationError::NameCollision(
+            "FaceDetection".into(),
+            "library/face-detection".into()
+        )),
+        GenCtx::feature("FaceDetection")
+            .app(default_app_ctx())
+            .build_in(fs.as_ref())
+    );
+}
+
+#[test]
+fn generate_warns_about_unregistered_modules() {
+    let fs = kotlin_project("test-app");
+    let application = application_path("test-app");
+    let source = fs.read_to_string(&application).unwrap();
+    fs.write(
+        &application,
+        &source.replace("    libraries = [\n        Platform::class\n    ],\n", ""),
+    )
+    .unwrap();
+
+    let ctx = GenCtx::library("face-detection")
+        .app(default_app_ctx())
+        .build_in(fs.as_ref())
+        .unwrap();
+    let plan = generate(ctx, &fs);
+
+    assert_eq!(
+        vec![format!(
+            "`face-detection` isn't registered in {}, its `@Scaffold` has no `libraries = [...]` \
+            argument, add `FaceDetection::class` to it",
+            application.display()
+        )],
+        plan.warnings
+    );
 }
 
-fn assert_content_eq(file_path: &Path, expected_contents: &str) {
-    let file = File::open(file_path).unwrap_or_else(|_| {
+fn assert_content_eq(fs: &MemoryFileSystem, file_path: &Path, expected_contents: &str) {
+    let contents = fs.read_to_string(file_path).unwrap_or_else(|_| {
         panic!("Couldn't find file {:?}", file_path);
     });
-    let contents = std::io::read_to_string(file);
-    assert_eq!(expected_contents, contents.unwrap());
+    assert_eq!(expected_contents, contents);
 }
 
 fn default_app_ctx() -> AppCtx {